@@ -14,6 +14,7 @@ export const Alert = forwardRef<HTMLDivElement, AlertProps>(function Alert(
 
 export function FormatButton(props: HeaderProps) {
   const [showError, setShowError] = useState(false);
+  const [error, setError] = useState("");
 
   const handleClose = (
     event?: React.SyntheticEvent | Event,
@@ -33,14 +34,11 @@ export function FormatButton(props: HeaderProps) {
         style={{ backgroundColor: "grey", marginLeft: "auto" }}
         onClick={(e) => {
           sp_format(props.code, props.settings as Settings)
-            .then((res) => {
-              if (res.length == 0 && props.code.trim().length > 0) {
-                setShowError(true);
-              } else {
-                props.setCode(res);
-              }
-            })
-            .catch((err) => console.log(err));
+            .then((res) => props.setCode(res))
+            .catch((err) => {
+              setError(String(err));
+              setShowError(true);
+            });
         }}
       >
         Format
@@ -52,7 +50,7 @@ export function FormatButton(props: HeaderProps) {
         anchorOrigin={{ vertical: "bottom", horizontal: "right" }}
       >
         <Alert onClose={handleClose} severity="error" sx={{ width: "100%" }}>
-          There is an error in your syntax.
+          {error}
         </Alert>
       </Snackbar>
     </>
@@ -13,6 +13,19 @@ export interface Settings {
   brace_wrapping_before_funcenum: boolean;
   brace_wrapping_before_methodmap: boolean;
   brace_wrapping_before_methodmap_property: boolean;
+  indent_style: "tabs" | "spaces";
+  indent_width: number;
+  max_line_width: number;
+  recover_from_syntax_errors: boolean;
+  remove_redundant_parentheses: boolean;
+  add_clarifying_parentheses: boolean;
+  bin_pack_arguments: boolean;
+  fill_array_literals: boolean;
+  trailing_comma_in_array_literals: boolean;
+  align_array_literal_columns: boolean;
+  break_before_logical_operators: boolean;
+  align_argument_names: boolean;
+  convert_old_syntax: boolean;
 }
 
 export interface HeaderProps {
@@ -16,5 +16,18 @@ export function makeDefaultSettings(): Settings {
     brace_wrapping_before_funcenum: true,
     brace_wrapping_before_methodmap: true,
     brace_wrapping_before_methodmap_property: true,
+    indent_style: "tabs",
+    indent_width: 4,
+    max_line_width: 80,
+    recover_from_syntax_errors: false,
+    remove_redundant_parentheses: false,
+    add_clarifying_parentheses: false,
+    bin_pack_arguments: false,
+    fill_array_literals: false,
+    trailing_comma_in_array_literals: false,
+    align_array_literal_columns: false,
+    break_before_logical_operators: false,
+    align_argument_names: false,
+    convert_old_syntax: false,
   };
 }
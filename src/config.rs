@@ -0,0 +1,70 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::settings::Settings;
+
+/// Names of the configuration files, by order of priority.
+pub const CONFIG_FILE_NAMES: [&str; 2] = [".spformat.toml", "spformat.json"];
+
+/// Find the configuration file which applies to a path, by walking up
+/// its ancestors until a directory containing one of the [`CONFIG_FILE_NAMES`]
+/// is found.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file (or directory) to find the configuration of.
+pub fn find_config_file(path: &Path) -> Option<PathBuf> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut directory = if path.is_dir() {
+        Some(path.as_path())
+    } else {
+        path.parent()
+    };
+    while let Some(current) = directory {
+        for name in CONFIG_FILE_NAMES {
+            let candidate = current.join(name);
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+        }
+        directory = current.parent();
+    }
+
+    None
+}
+
+/// Read the settings from a configuration file.
+/// `.json` files are parsed as JSON, anything else as TOML.
+///
+/// # Arguments
+///
+/// * `path` - The path of the configuration file.
+pub fn load_config_file(path: &Path) -> anyhow::Result<Settings> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Could not read the configuration file {}.", path.display()))?;
+    let settings = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => serde_json::from_str(&content)
+            .with_context(|| format!("Invalid configuration file {}.", path.display()))?,
+        _ => toml::from_str(&content)
+            .with_context(|| format!("Invalid configuration file {}.", path.display()))?,
+    };
+
+    Ok(settings)
+}
+
+/// Get the settings which apply to a path. Falls back to the default settings
+/// if no configuration file is found.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file (or directory) to get the settings of.
+pub fn discover_settings(path: &Path) -> anyhow::Result<Settings> {
+    match find_config_file(path) {
+        Some(config_path) => load_config_file(&config_path),
+        None => Ok(Settings::default()),
+    }
+}
@@ -0,0 +1,210 @@
+//! Report of the node kinds of the grammar which the writers know how to format.
+//!
+//! The formatter is run over a corpus of sources, and every named node is
+//! classified by what the writers did with it: formatted by a dedicated writer,
+//! copied verbatim by the generic fallback, or reported as unexpected.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    ops::Range,
+};
+
+use tree_sitter::{Language, Node};
+
+use crate::{
+    diagnostics::DiagnosticKind,
+    error::FormatError,
+    formatter::{new_writer, parse, syntax_error},
+    parser,
+    settings::Settings,
+    writers::source_file::write_source_file,
+};
+
+/// What the writers did with the nodes of a kind, over the whole corpus.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KindCoverage {
+    /// Number of nodes formatted by a dedicated writer.
+    pub formatted: usize,
+    /// Number of nodes copied verbatim instead of being formatted.
+    pub verbatim: usize,
+    /// Number of nodes reported as unexpected by a writer.
+    pub unexpected: usize,
+}
+
+impl KindCoverage {
+    fn is_seen(&self) -> bool {
+        self.formatted + self.verbatim + self.unexpected > 0
+    }
+}
+
+/// Coverage of the named node kinds of the grammar over a corpus of sources.
+pub struct Coverage {
+    language: Language,
+    /// Coverage of each named node kind, sorted by name.
+    pub kinds: BTreeMap<String, KindCoverage>,
+    /// Number of sources added to the report.
+    pub sources: usize,
+}
+
+impl Coverage {
+    /// Build an empty report listing the named node kinds of the SourcePawn grammar.
+    pub fn new() -> Self {
+        let language: Language = tree_sitter_sourcepawn::language().into();
+        let mut kinds = BTreeMap::new();
+        for id in 0..language.node_kind_count() {
+            let id = u16::try_from(id).unwrap();
+            if !language.node_kind_is_named(id) {
+                continue;
+            }
+            // The hidden rules of the grammar never appear in a tree.
+            match language.node_kind_for_id(id) {
+                Some(kind) if !kind.starts_with('_') => {
+                    kinds.insert(kind.to_string(), KindCoverage::default());
+                }
+                _ => (),
+            }
+        }
+
+        Self {
+            language,
+            kinds,
+            sources: 0,
+        }
+    }
+
+    /// Format a source and add what the writers did with its nodes to the report.
+    /// The nodes nested in a node which was copied verbatim, reported as unexpected
+    /// or skipped because of a syntax error are never reached, and are not counted.
+    ///
+    /// # Arguments
+    ///
+    /// * `input`    - The source to format.
+    /// * `settings` - The settings of the formatter.
+    pub fn add_source(&mut self, input: &String, settings: &Settings) -> Result<(), FormatError> {
+        let mut parser = parser::sourcepawn(&self.language)?;
+        let parsed = parse(input, &mut parser)?;
+        if parsed.root_node().has_error() && !settings.recover_from_syntax_errors {
+            return Err(syntax_error(&parsed.root_node()));
+        }
+        let mut writer = new_writer(input, &self.language, settings);
+        write_source_file(parsed.root_node(), &mut writer)?;
+
+        let mut unreached = HashSet::new();
+        for (kind, range) in writer.verbatim {
+            self.kind_mut(&kind).verbatim += 1;
+            unreached.insert((kind, range));
+        }
+        for diagnostic in writer.diagnostics {
+            match diagnostic.kind {
+                DiagnosticKind::UnexpectedNode => {
+                    self.kind_mut(&diagnostic.node_kind).unexpected += 1
+                }
+                // The declaration was still formatted, in the old syntax.
+                DiagnosticKind::UnconvertedOldSyntax => continue,
+                DiagnosticKind::SkippedSyntaxError => (),
+            }
+            unreached.insert((diagnostic.node_kind, diagnostic.range));
+        }
+        self.count_formatted(&parsed.root_node(), &unreached);
+        self.sources += 1;
+
+        Ok(())
+    }
+
+    /// Returns the kinds which were formatted by a dedicated writer at least once.
+    pub fn formatted(&self) -> Vec<&str> {
+        self.filter_kinds(|coverage| coverage.formatted > 0)
+    }
+
+    /// Returns the kinds which were copied verbatim at least once.
+    pub fn verbatim(&self) -> Vec<&str> {
+        self.filter_kinds(|coverage| coverage.verbatim > 0)
+    }
+
+    /// Returns the kinds which were reported as unexpected at least once.
+    pub fn unexpected(&self) -> Vec<&str> {
+        self.filter_kinds(|coverage| coverage.unexpected > 0)
+    }
+
+    /// Returns the kinds which do not appear in the corpus.
+    pub fn unseen(&self) -> Vec<&str> {
+        self.filter_kinds(|coverage| !coverage.is_seen())
+    }
+
+    fn kind_mut(&mut self, kind: &str) -> &mut KindCoverage {
+        self.kinds.entry(kind.to_string()).or_default()
+    }
+
+    fn filter_kinds<F>(&self, predicate: F) -> Vec<&str>
+    where
+        F: Fn(&KindCoverage) -> bool,
+    {
+        self.kinds
+            .iter()
+            .filter(|(_, coverage)| predicate(coverage))
+            .map(|(kind, _)| kind.as_str())
+            .collect()
+    }
+
+    /// Count the named nodes of a tree which were formatted, stopping at the
+    /// nodes which were not reached by a dedicated writer.
+    ///
+    /// # Arguments
+    ///
+    /// * `node`      - The root of the tree to count the nodes of.
+    /// * `unreached` - Kinds and byte ranges of the nodes which were not formatted.
+    fn count_formatted(&mut self, node: &Node, unreached: &HashSet<(String, Range<usize>)>) {
+        let range =
+            usize::try_from(node.start_byte()).unwrap()..usize::try_from(node.end_byte()).unwrap();
+        if node.is_error() || unreached.contains(&(node.kind().to_string(), range)) {
+            return;
+        }
+        if node.is_named() {
+            self.kind_mut(&node.kind()).formatted += 1;
+        }
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            self.count_formatted(&child, unreached);
+        }
+    }
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Coverage of {} node kinds over {} source(s).",
+            self.kinds.len(),
+            self.sources
+        )?;
+        let sections: [(&str, Vec<&str>, fn(&KindCoverage) -> usize); 3] = [
+            ("Formatted", self.formatted(), |coverage| coverage.formatted),
+            ("Copied verbatim", self.verbatim(), |coverage| {
+                coverage.verbatim
+            }),
+            ("Unexpected", self.unexpected(), |coverage| {
+                coverage.unexpected
+            }),
+        ];
+        for (title, kinds, count) in sections {
+            writeln!(f, "\n{} ({}):", title, kinds.len())?;
+            for kind in kinds {
+                writeln!(f, "  {} ({})", kind, count(&self.kinds[kind]))?;
+            }
+        }
+        let unseen = self.unseen();
+        writeln!(f, "\nNot in the corpus ({}):", unseen.len())?;
+        for kind in unseen {
+            writeln!(f, "  {}", kind)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use std::{fmt, ops::Range};
+
+use serde::Serialize;
+use tree_sitter::Node;
+
+/// Kinds of diagnostics the formatter can report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    /// A writer met a node it does not know how to format.
+    UnexpectedNode,
+    /// A node containing a syntax error was copied verbatim.
+    SkippedSyntaxError,
+    /// An old syntax declaration could not be converted to the new syntax, and
+    /// was kept as is.
+    UnconvertedOldSyntax,
+}
+
+/// A problem met by the formatter while writing a node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// What the diagnostic is about.
+    pub kind: DiagnosticKind,
+    /// Kind of the node which triggered the diagnostic.
+    pub node_kind: String,
+    /// Byte range of the node in the source.
+    pub range: Range<usize>,
+    /// Zero-based row of the start of the node.
+    pub row: u32,
+    /// Zero-based column of the start of the node.
+    pub column: u32,
+    /// Name of the writer function which reported the diagnostic.
+    pub writer: &'static str,
+}
+
+impl Diagnostic {
+    /// Build a diagnostic about a node.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind`   - What the diagnostic is about.
+    /// * `node`   - The node which triggered the diagnostic.
+    /// * `writer` - Name of the writer function which reports the diagnostic.
+    pub fn new(kind: DiagnosticKind, node: &Node, writer: &'static str) -> Self {
+        Self {
+            kind,
+            node_kind: node.kind().to_string(),
+            range: usize::try_from(node.start_byte()).unwrap()
+                ..usize::try_from(node.end_byte()).unwrap(),
+            row: node.start_position().row(),
+            column: node.start_position().column(),
+            writer,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            DiagnosticKind::UnexpectedNode => write!(
+                f,
+                "{}:{}: unexpected kind {} in {}",
+                self.row + 1,
+                self.column + 1,
+                self.node_kind,
+                self.writer
+            ),
+            DiagnosticKind::SkippedSyntaxError => write!(
+                f,
+                "{}:{}: syntax error, {} copied verbatim in {}",
+                self.row + 1,
+                self.column + 1,
+                self.node_kind,
+                self.writer
+            ),
+            DiagnosticKind::UnconvertedOldSyntax => write!(
+                f,
+                "{}:{}: {} cannot be converted to the new syntax in {}",
+                self.row + 1,
+                self.column + 1,
+                self.node_kind,
+                self.writer
+            ),
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Intermediate document representation used to break long lines,
+//! in the spirit of Wadler's "A prettier printer".
+//!
+//! Writers build a [`Doc`] out of text, groups, lines and indents, and [`print`]
+//! lays it out: a group is printed on a single line if it fits in the remaining
+//! width, otherwise each of its lines is turned into a line break.
+
+/// A document to lay out.
+#[derive(Debug, Clone)]
+pub enum Doc {
+    /// Text which never contains a line break of its own.
+    Text(String),
+    /// A space if the enclosing group is flat, a line break otherwise.
+    Line,
+    /// Nothing if the enclosing group is flat, a line break otherwise.
+    SoftLine,
+    /// A line break, which also breaks all the enclosing groups.
+    HardLine,
+    /// Forces all the enclosing groups to break, prints nothing.
+    BreakParent,
+    /// A sequence of documents.
+    Concat(Vec<Doc>),
+    /// A document which is printed flat if it fits on the line.
+    Group(Box<Doc>),
+    /// A document whose line breaks are indented one level deeper.
+    Indent(Box<Doc>),
+    /// The first document if the enclosing group is broken, the second otherwise.
+    IfBreak(Box<Doc>, Box<Doc>),
+}
+
+impl Doc {
+    pub fn text<S: Into<String>>(text: S) -> Self {
+        Doc::Text(text.into())
+    }
+
+    pub fn concat(docs: Vec<Doc>) -> Self {
+        Doc::Concat(docs)
+    }
+
+    pub fn group(doc: Doc) -> Self {
+        Doc::Group(Box::new(doc))
+    }
+
+    pub fn indent(doc: Doc) -> Self {
+        Doc::Indent(Box::new(doc))
+    }
+
+    pub fn if_break(break_doc: Doc, flat_doc: Doc) -> Self {
+        Doc::IfBreak(Box::new(break_doc), Box::new(flat_doc))
+    }
+
+    /// Join documents with a separator.
+    ///
+    /// # Arguments
+    ///
+    /// * `docs`      - The documents to join.
+    /// * `separator` - The document to insert between each document.
+    pub fn join(docs: Vec<Doc>, separator: Doc) -> Self {
+        let mut joined = vec![];
+        for (i, doc) in docs.into_iter().enumerate() {
+            if i > 0 {
+                joined.push(separator.clone());
+            }
+            joined.push(doc);
+        }
+
+        Doc::Concat(joined)
+    }
+
+    /// Returns true if the document contains a hard line or forces its parents to break.
+    fn forces_break(&self) -> bool {
+        match self {
+            Doc::HardLine | Doc::BreakParent => true,
+            Doc::Concat(docs) => docs.iter().any(|doc| doc.forces_break()),
+            Doc::Group(doc) | Doc::Indent(doc) => doc.forces_break(),
+            Doc::IfBreak(break_doc, flat_doc) => {
+                break_doc.forces_break() || flat_doc.forces_break()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Options of the printer.
+pub struct PrintOptions<'a> {
+    /// Maximum width of a line.
+    pub max_line_width: usize,
+    /// String of one indentation level.
+    pub indent_string: &'a str,
+    /// Width of a tab, used to compute the width of the indentation.
+    pub tab_width: usize,
+    /// Indentation level of the lines broken by the printer.
+    pub indent: usize,
+    /// Column at which the document starts.
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// Returns the width of a string, with tabs expanded.
+///
+/// # Arguments
+///
+/// * `text`      - The string to measure.
+/// * `column`    - The column the string starts at.
+/// * `tab_width` - The width of a tab.
+pub fn text_width(text: &str, column: usize, tab_width: usize) -> usize {
+    let mut end = column;
+    for c in text.chars() {
+        if c == '\t' {
+            end += tab_width - end % tab_width;
+        } else {
+            end += 1;
+        }
+    }
+
+    end - column
+}
+
+/// Lay out a document.
+///
+/// # Arguments
+///
+/// * `doc`     - The document to print.
+/// * `options` - The options of the printer.
+pub fn print(doc: &Doc, options: &PrintOptions) -> String {
+    let tab_width = options.tab_width.max(1);
+    let indent_width = text_width(options.indent_string, 0, tab_width);
+    let mut output = String::new();
+    let mut column = options.column;
+    let mut commands: Vec<(usize, Mode, &Doc)> = vec![(options.indent, Mode::Break, doc)];
+
+    while let Some((indent, mode, doc)) = commands.pop() {
+        match doc {
+            Doc::Text(text) => {
+                column += text_width(text, column, tab_width);
+                output.push_str(text);
+            }
+            Doc::Concat(docs) => {
+                for doc in docs.iter().rev() {
+                    commands.push((indent, mode, doc));
+                }
+            }
+            Doc::Indent(doc) => commands.push((indent + 1, mode, doc)),
+            Doc::Group(doc) => {
+                let mode = if mode == Mode::Flat
+                    || (!doc.forces_break()
+                        && fits(
+                            doc,
+                            &commands,
+                            options.max_line_width as isize - column as isize,
+                        )) {
+                    Mode::Flat
+                } else {
+                    Mode::Break
+                };
+                commands.push((indent, mode, doc));
+            }
+            Doc::IfBreak(break_doc, flat_doc) => {
+                if mode == Mode::Break {
+                    commands.push((indent, mode, break_doc));
+                } else {
+                    commands.push((indent, mode, flat_doc));
+                }
+            }
+            Doc::Line | Doc::SoftLine if mode == Mode::Flat => {
+                if let Doc::Line = doc {
+                    output.push(' ');
+                    column += 1;
+                }
+            }
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                // Don't leave trailing whitespaces before the break.
+                let trimmed_len = output.trim_end_matches(' ').len();
+                output.truncate(trimmed_len);
+                output.push('\n');
+                output.push_str(options.indent_string.repeat(indent).as_str());
+                column = indent * indent_width;
+            }
+            Doc::BreakParent => (),
+        }
+    }
+
+    output
+}
+
+/// Returns true if a document fits in the remaining width when printed flat,
+/// taking into account the content which follows it on the same line.
+///
+/// # Arguments
+///
+/// * `doc`       - The document to measure.
+/// * `rest`      - The commands which will be printed after the document.
+/// * `remaining` - The remaining width on the line.
+fn fits(doc: &Doc, rest: &[(usize, Mode, &Doc)], mut remaining: isize) -> bool {
+    let mut stack: Vec<(Mode, &Doc)> = vec![(Mode::Flat, doc)];
+    let mut rest_index = rest.len();
+
+    while remaining >= 0 {
+        let (mode, doc) = match stack.pop() {
+            Some(command) => command,
+            None => {
+                if rest_index == 0 {
+                    return true;
+                }
+                rest_index -= 1;
+                let (_, mode, doc) = rest[rest_index];
+                (mode, doc)
+            }
+        };
+        match doc {
+            Doc::Text(text) => remaining -= text.chars().count() as isize,
+            Doc::Concat(docs) => {
+                for doc in docs.iter().rev() {
+                    stack.push((mode, doc));
+                }
+            }
+            Doc::Group(doc) | Doc::Indent(doc) => stack.push((mode, doc)),
+            Doc::IfBreak(break_doc, flat_doc) => {
+                if mode == Mode::Break {
+                    stack.push((mode, break_doc));
+                } else {
+                    stack.push((mode, flat_doc));
+                }
+            }
+            Doc::Line => {
+                if mode == Mode::Break {
+                    return true;
+                }
+                remaining -= 1;
+            }
+            Doc::SoftLine => {
+                if mode == Mode::Break {
+                    return true;
+                }
+            }
+            Doc::HardLine => return true,
+            Doc::BreakParent => (),
+        }
+    }
+
+    false
+}
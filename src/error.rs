@@ -0,0 +1,70 @@
+use std::{fmt, str::Utf8Error};
+
+use thiserror::Error;
+
+/// A node of the syntax tree which could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// Zero-based row of the start of the node.
+    pub row: u32,
+    /// Zero-based column of the start of the node.
+    pub column: u32,
+    /// The token which was expected, if the node is a `MISSING` node.
+    /// `None` if the node is an `ERROR` node.
+    pub missing: Option<String>,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.missing {
+            Some(kind) => write!(
+                f,
+                "{}:{}: missing `{}`",
+                self.row + 1,
+                self.column + 1,
+                kind
+            ),
+            None => write!(f, "{}:{}: unexpected syntax", self.row + 1, self.column + 1),
+        }
+    }
+}
+
+/// Errors which can occur while formatting SourcePawn code.
+#[derive(Error, Debug)]
+pub enum FormatError {
+    /// The source has syntax errors, it was not formatted.
+    #[error("syntax error(s) at {}", join_syntax_errors(.0))]
+    Syntax(Vec<SyntaxError>),
+
+    /// The source (or a node of the syntax tree) is not valid UTF-8.
+    #[error("invalid UTF-8: {0}")]
+    Utf8(#[from] Utf8Error),
+
+    /// The parser could not be created or could not parse the source.
+    #[error("could not set up the parser: {0}")]
+    Parser(String),
+
+    /// The formatted code does not have the same tokens as the source,
+    /// it was not returned.
+    #[error("the formatted code differs from the source: {0}")]
+    Verification(String),
+
+    /// Formatting the formatted code changed it again.
+    #[error("the formatting is not idempotent, line {line} changed from `{first}` to `{second}`")]
+    NotIdempotent {
+        /// One-based number of the first line which changed.
+        line: usize,
+        /// The line after the first pass.
+        first: String,
+        /// The line after the second pass.
+        second: String,
+    },
+}
+
+fn join_syntax_errors(errors: &[SyntaxError]) -> String {
+    errors
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -1,39 +1,377 @@
 use super::parser;
 use crate::{
+    diagnostics::Diagnostic,
+    error::{FormatError, SyntaxError},
     settings::Settings,
-    writers::{self, source_file::write_source_file},
+    writers::{
+        self, children_with_directives,
+        source_file::{write_source_file, write_top_level_node},
+        statements::write_statement,
+        write_comment, Child,
+    },
 };
-use std::collections::HashSet;
-use tree_sitter::Language;
+use std::{borrow::Borrow, collections::HashSet, ops::Range, str::Utf8Error};
+use tree_sitter::{Language, Node, Parser, Tree};
+
+/// Kinds of the nodes whose children are indented one level deeper than them.
+const INDENTING_KINDS: [&str; 5] = [
+    "block",
+    "switch_statement",
+    "enum_struct",
+    "methodmap",
+    "methodmap_property",
+];
+
+/// The output of the formatter.
+#[derive(Debug, Clone)]
+pub struct Formatted {
+    /// The formatted source.
+    pub output: String,
+    /// The problems met while formatting the source.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// An edit to apply to the source, replacing a range of bytes with new text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    /// The range of bytes of the source to replace.
+    pub range: Range<usize>,
+    /// The text to replace the range with.
+    pub new_text: String,
+}
 
 pub fn format_string_language(
     input: &String,
     language: Language,
     settings: &Settings,
-) -> anyhow::Result<String> {
+) -> Result<Formatted, FormatError> {
     let mut parser = parser::sourcepawn(&language)?;
-    let parsed = parser.parse(&input, None)?.unwrap();
-    if parsed.root_node().has_error() {
+    format_string_parser(input, &mut parser, &language, settings)
+}
+
+/// Format a source with an existing parser, which can be reused across calls.
+///
+/// # Arguments
+///
+/// * `input`    - The source to format.
+/// * `parser`   - The parser of the SourcePawn language.
+/// * `language` - The SourcePawn language.
+/// * `settings` - The settings of the formatter.
+pub fn format_string_parser(
+    input: &String,
+    parser: &mut Parser,
+    language: &Language,
+    settings: &Settings,
+) -> Result<Formatted, FormatError> {
+    let parsed = parse(input, parser)?;
+    if parsed.root_node().has_error() && !settings.recover_from_syntax_errors {
         // Do not try to format, there is an error in the syntax.
-        return Ok("".to_string());
+        return Err(syntax_error(&parsed.root_node()));
     }
-    #[cfg(debug_assertions)]
-    println!("{}", parsed.root_node().to_sexp());
+    let mut writer = new_writer(input, language, settings);
+    write_source_file(parsed.root_node(), &mut writer)?;
+    Ok(Formatted {
+        output: writer.output,
+        diagnostics: writer.diagnostics,
+    })
+}
+
+/// Format the smallest top level declarations or statements of a source which
+/// cover a range of bytes, and return the edits to apply to the source.
+/// Everything outside of these nodes is left untouched.
+///
+/// # Arguments
+///
+/// * `input`    - The source to format.
+/// * `language` - The SourcePawn language.
+/// * `range`    - The range of bytes to format.
+/// * `settings` - The settings of the formatter.
+pub fn format_range_language(
+    input: &String,
+    language: Language,
+    range: Range<usize>,
+    settings: &Settings,
+) -> Result<Vec<TextEdit>, FormatError> {
+    let mut parser = parser::sourcepawn(&language)?;
+    format_range_parser(input, &mut parser, &language, range, settings)
+}
+
+/// Format a range of a source with an existing parser, which can be reused across calls.
+/// See [`format_range_language`].
+///
+/// # Arguments
+///
+/// * `input`    - The source to format.
+/// * `parser`   - The parser of the SourcePawn language.
+/// * `language` - The SourcePawn language.
+/// * `range`    - The range of bytes to format.
+/// * `settings` - The settings of the formatter.
+pub fn format_range_parser(
+    input: &String,
+    parser: &mut Parser,
+    language: &Language,
+    range: Range<usize>,
+    settings: &Settings,
+) -> Result<Vec<TextEdit>, FormatError> {
+    let parsed = parse(input, parser)?;
+    let units = range_units(parsed.root_node(), &range, input.as_bytes());
+    if !settings.recover_from_syntax_errors {
+        if let Some(unit) = units.iter().find(|unit| unit.has_error()) {
+            // Do not try to format, there is an error in the syntax.
+            return Err(syntax_error(unit));
+        }
+    }
+    let mut writer = new_writer(input, language, settings);
+    let mut edits = vec![];
+    for unit in units {
+        writer.indent = nesting_level(&unit);
+        write_range_unit(unit, &mut writer)?;
+        let output = std::mem::take(&mut writer.output);
+        let start = usize::try_from(unit.start_byte()).unwrap();
+        let line_start = input[..start].rfind('\n').map_or(0, |index| index + 1);
+        let mut range = start..usize::try_from(unit.end_byte()).unwrap();
+        let mut new_text = output.trim().to_string();
+        if input[line_start..start].trim().is_empty() {
+            // The node starts its line, replace the indentation of the source as well.
+            range.start = line_start;
+            new_text.insert_str(0, &writer.indent_string.repeat(writer.indent));
+        }
+        if new_text != input[range.clone()] {
+            edits.push(TextEdit { range, new_text });
+        }
+    }
+
+    Ok(edits)
+}
+
+/// Compare the outputs of two formatting passes, and describe the first line
+/// which differs.
+///
+/// # Arguments
+///
+/// * `first`  - The output of the first pass.
+/// * `second` - The output of the second pass, formatted from the first one.
+pub fn compare_passes(first: &str, second: &str) -> Result<(), FormatError> {
+    let mut first_lines = first.split('\n');
+    let mut second_lines = second.split('\n');
+    let mut line = 0;
+    loop {
+        line += 1;
+        match (first_lines.next(), second_lines.next()) {
+            (None, None) => return Ok(()),
+            (Some(first_line), Some(second_line)) if first_line == second_line => continue,
+            (first_line, second_line) => {
+                return Err(FormatError::NotIdempotent {
+                    line,
+                    first: first_line.unwrap_or("<end of file>").to_string(),
+                    second: second_line.unwrap_or("<end of file>").to_string(),
+                })
+            }
+        }
+    }
+}
+
+/// Convert a range of lines to the range of bytes it covers.
+///
+/// # Arguments
+///
+/// * `input` - The source the lines belong to.
+/// * `lines` - The range of 0-based line numbers, the end is excluded.
+pub fn line_range_to_byte_range(input: &str, lines: Range<usize>) -> Range<usize> {
+    let line_start = |line: usize| {
+        if line == 0 {
+            return 0;
+        }
+        input
+            .match_indices('\n')
+            .nth(line - 1)
+            .map_or(input.len(), |(index, _)| index + 1)
+    };
+
+    line_start(lines.start)..line_start(lines.end)
+}
+
+pub(crate) fn parse(input: &String, parser: &mut Parser) -> Result<Tree, FormatError> {
+    parser
+        .parse(&input, None)
+        .map_err(|err| FormatError::Parser(err.to_string()))?
+        .ok_or_else(|| FormatError::Parser("the parsing was cancelled".to_string()))
+}
+
+pub(crate) fn new_writer<'a>(
+    input: &'a String,
+    language: &'a Language,
+    settings: &'a Settings,
+) -> writers::Writer<'a> {
     let mut writer = writers::Writer {
         output: String::new(),
         source: input.as_bytes(),
-        language: &language,
+        language: language,
         indent: 0,
-        indent_string: "\t".to_string(),
-        skip: 0,
+        indent_string: settings.indent_string(),
         settings: settings,
         _statement_kinds: HashSet::new(),
         _expression_kinds: HashSet::new(),
         _literal_kinds: HashSet::new(),
+        diagnostics: vec![],
+        verbatim: vec![],
     };
     build_writer(&mut writer);
-    write_source_file(parsed.root_node(), &mut writer)?;
-    Ok(writer.output)
+
+    writer
+}
+
+pub(crate) fn syntax_error(node: &Node) -> FormatError {
+    let mut errors = vec![];
+    collect_syntax_errors(node, &mut errors);
+
+    FormatError::Syntax(errors)
+}
+
+/// Returns true if a node intersects a range of bytes. An empty range
+/// intersects the nodes it touches.
+///
+/// # Arguments
+///
+/// * `node`  - The node to check.
+/// * `range` - The range of bytes.
+fn intersects(node: &Node, range: &Range<usize>) -> bool {
+    let start = usize::try_from(node.start_byte()).unwrap();
+    let end = usize::try_from(node.end_byte()).unwrap();
+    if range.is_empty() {
+        return start <= range.start && range.start <= end;
+    }
+
+    start < range.end && range.start < end
+}
+
+/// Returns true if a node contains a range of bytes.
+///
+/// # Arguments
+///
+/// * `node`  - The node to check.
+/// * `range` - The range of bytes.
+fn contains(node: &Node, range: &Range<usize>) -> bool {
+    usize::try_from(node.start_byte()).unwrap() <= range.start
+        && range.end <= usize::try_from(node.end_byte()).unwrap()
+}
+
+/// Find the nodes to format to cover a range of bytes. These are the children
+/// of the source file, or the statements of the innermost block which covers the range.
+/// The nodes disabled by a directive are never formatted.
+///
+/// # Arguments
+///
+/// * `container` - The source file or block to search the children of.
+/// * `range`     - The range of bytes to cover.
+/// * `source`    - The source of the container.
+fn range_units<'a>(container: Node<'a>, range: &Range<usize>, source: &[u8]) -> Vec<Node<'a>> {
+    let units: Vec<Node> = children_with_directives(&container, source)
+        .into_iter()
+        .filter_map(|child| match child {
+            Child::Formatted(child) => Some(child),
+            Child::Disabled(..) => None,
+        })
+        .filter(|child| {
+            let kind = child.kind();
+            kind != "{" && kind != "}" && intersects(child, range)
+        })
+        .collect();
+    if units.len() == 1 {
+        if let Some(block) = enclosing_block(&units[0], range) {
+            let inner_units = range_units(block, range, source);
+            if !inner_units.is_empty() {
+                return inner_units;
+            }
+        }
+    }
+
+    units
+}
+
+/// Find the outermost block inside of a node which contains a range of bytes.
+///
+/// # Arguments
+///
+/// * `node`  - The node to search.
+/// * `range` - The range of bytes.
+fn enclosing_block<'a>(node: &Node<'a>, range: &Range<usize>) -> Option<Node<'a>> {
+    let mut cursor = node.walk();
+    let child = node
+        .children(&mut cursor)
+        .find(|child| contains(child, range))?;
+    if child.kind() == "block" {
+        return Some(child);
+    }
+
+    enclosing_block(&child, range)
+}
+
+/// Write a node found by [`range_units`].
+///
+/// # Arguments
+///
+/// * `node`   - The node to write.
+/// * `writer` - The writer object.
+fn write_range_unit(node: Node, writer: &mut writers::Writer) -> Result<(), Utf8Error> {
+    let parent_kind = node.parent().map(|parent| parent.kind().to_string());
+    if parent_kind.as_deref() == Some("block") {
+        match node.kind().borrow() {
+            "comment" => write_comment(&node, writer),
+            // Indent the statement like in its block, to terminate it with a `;`.
+            // The indentation is trimmed by the caller.
+            _ => write_statement(node, writer, true, false),
+        }
+    } else {
+        write_top_level_node(node, writer)
+    }
+}
+
+/// Returns the indentation level of a node, from the containers it is nested in,
+/// as if the whole source was formatted.
+///
+/// # Arguments
+///
+/// * `node` - The node to get the indentation level of.
+fn nesting_level(node: &Node) -> usize {
+    let mut level = 0;
+    let mut parent = node.parent();
+    while let Some(ancestor) = parent {
+        if INDENTING_KINDS.contains(&ancestor.kind().borrow()) {
+            level += 1;
+        }
+        parent = ancestor.parent();
+    }
+
+    level
+}
+
+/// Collect the `ERROR` and `MISSING` nodes of a tree.
+///
+/// # Arguments
+///
+/// * `node`   - The root of the tree to search.
+/// * `errors` - The vector to push the errors into.
+fn collect_syntax_errors(node: &Node, errors: &mut Vec<SyntaxError>) {
+    if node.is_error() || node.is_missing() {
+        errors.push(SyntaxError {
+            row: node.start_position().row(),
+            column: node.start_position().column(),
+            missing: if node.is_missing() {
+                Some(node.kind().to_string())
+            } else {
+                None
+            },
+        });
+        return;
+    }
+    if !node.has_error() {
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_syntax_errors(&child, errors);
+    }
 }
 
 fn build_writer(writer: &mut writers::Writer) {
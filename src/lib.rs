@@ -1,31 +1,113 @@
+#[cfg(not(target_arch = "wasm32"))]
+pub mod config;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod coverage;
+pub mod diagnostics;
+mod doc;
+pub mod error;
 mod formatter;
 mod language;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod lsp;
 mod parser;
 pub mod settings;
+#[cfg(not(target_arch = "wasm32"))]
+mod verify;
 mod writers;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+use error::FormatError;
 use formatter::format_string_language;
+#[cfg(not(target_arch = "wasm32"))]
+use formatter::{compare_passes, format_range_language, format_string_parser};
+pub use formatter::{line_range_to_byte_range, Formatted, TextEdit};
 use settings::Settings;
+#[cfg(not(target_arch = "wasm32"))]
+use std::ops::Range;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn format_string(input: &String, settings: Settings) -> Result<String, FormatError> {
+    Ok(format_string_with_diagnostics(input, settings)?.output)
+}
+
+/// Format SourcePawn code and return the diagnostics reported by the writers
+/// alongside the formatted code.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn format_string_with_diagnostics(
+    input: &String,
+    settings: Settings,
+) -> Result<Formatted, FormatError> {
+    let language = tree_sitter_sourcepawn::language().into();
+    format_string_language(&input, language, &settings)
+}
 
+/// Format SourcePawn code, then check that the formatted code has the same
+/// tokens and comments as the input. Returns a [`FormatError::Verification`]
+/// error describing the first difference if it does not.
 #[cfg(not(target_arch = "wasm32"))]
-pub fn format_string(input: &String, settings: Settings) -> anyhow::Result<String> {
+pub fn format_string_verified(
+    input: &String,
+    settings: Settings,
+) -> Result<Formatted, FormatError> {
     let language = tree_sitter_sourcepawn::language().into();
-    let output = format_string_language(&input, language, &settings)
-        .expect("An error has occured while generating the Sourcepawn code.");
+    let mut parser = parser::sourcepawn(&language)?;
+    let formatted = format_string_parser(&input, &mut parser, &language, &settings)?;
+    verify::verify_output(input, &formatted.output, &mut parser, &settings)?;
 
-    Ok(output)
+    Ok(formatted)
+}
+
+/// Format SourcePawn code twice, and check that the second pass leaves the
+/// formatted code unchanged. Returns a [`FormatError::NotIdempotent`] error
+/// describing the first line which changed if it does not.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn check_idempotency(input: &String, settings: Settings) -> Result<Formatted, FormatError> {
+    let language = tree_sitter_sourcepawn::language().into();
+    let mut parser = parser::sourcepawn(&language)?;
+    let first = format_string_parser(&input, &mut parser, &language, &settings)?;
+    let second = format_string_parser(&first.output, &mut parser, &language, &settings)?;
+    compare_passes(&first.output, &second.output)?;
+
+    Ok(first)
+}
+
+/// Format the top level declarations or statements of SourcePawn code which
+/// cover a range of bytes, and return the edits to apply to the code.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn format_range(
+    input: &String,
+    range: Range<usize>,
+    settings: Settings,
+) -> Result<Vec<TextEdit>, FormatError> {
+    let language = tree_sitter_sourcepawn::language().into();
+    format_range_language(&input, language, range, &settings)
+}
+
+/// Format the top level declarations or statements of SourcePawn code which
+/// cover a range of 0-based lines, the end being excluded, and return the edits
+/// to apply to the code.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn format_lines(
+    input: &String,
+    lines: Range<usize>,
+    settings: Settings,
+) -> Result<Vec<TextEdit>, FormatError> {
+    format_range(input, line_range_to_byte_range(input, lines), settings)
 }
 
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub async fn sp_format(input: String, val: JsValue) -> Result<String, JsValue> {
     tree_sitter::TreeSitter::init().await?;
-    let language = language::sourcepawn().await.unwrap();
-    let settings: Settings = val.into_serde().unwrap();
-    let output = format_string_language(&input, language, &settings)
-        .expect("An error has occured while generating the SourcePawn code.");
-    Ok(output)
+    let language = language::sourcepawn()
+        .await
+        .map_err(|err| JsValue::from_str(&err.to_string()))?;
+    let settings: Settings = val
+        .into_serde()
+        .map_err(|err| JsValue::from_str(&format!("Invalid settings: {}", err)))?;
+    let formatted = format_string_language(&input, language, &settings)
+        .map_err(|err| JsValue::from_str(&err.to_string()))?;
+    Ok(formatted.output)
 }
@@ -0,0 +1,421 @@
+//! Language server exposing the formatter over JSON-RPC on stdio.
+//!
+//! The server keeps the documents opened by the client in memory, and formats
+//! them on `textDocument/formatting`, `textDocument/rangeFormatting` and
+//! `textDocument/onTypeFormatting` requests.
+
+use std::{collections::HashMap, ops};
+
+use anyhow::{anyhow, Context};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeConfiguration, DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as _, ShowMessage,
+    },
+    request::{Formatting, OnTypeFormatting, RangeFormatting, Request as _},
+    DidChangeConfigurationParams, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentFormattingParams, DocumentOnTypeFormattingOptions,
+    DocumentOnTypeFormattingParams, DocumentRangeFormattingParams, FormattingOptions,
+    InitializeParams, MessageType, OneOf, Position, Range, ServerCapabilities, ShowMessageParams,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Url,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tree_sitter::{Language, Parser};
+
+use crate::{
+    config::{find_config_file, load_config_file},
+    error::FormatError,
+    formatter::{format_range_parser, format_string_parser, line_range_to_byte_range},
+    parser,
+    settings::{IndentStyle, Settings},
+};
+
+/// Run the language server on stdio until the client asks it to exit.
+pub fn run() -> anyhow::Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    serve(&connection)?;
+    io_threads.join()?;
+
+    Ok(())
+}
+
+/// Serve a client on an established connection, e.g. an in-memory one, until
+/// the client asks the server to exit.
+///
+/// # Arguments
+///
+/// * `connection` - The connection to the client.
+pub fn serve(connection: &Connection) -> anyhow::Result<()> {
+    let capabilities = serde_json::to_value(ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        document_range_formatting_provider: Some(OneOf::Left(true)),
+        document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+            first_trigger_character: "}".to_string(),
+            more_trigger_character: Some(vec![";".to_string()]),
+        }),
+        ..Default::default()
+    })?;
+    let params: InitializeParams = serde_json::from_value(connection.initialize(capabilities)?)?;
+
+    let mut server = Server::new()?;
+    if let Some(options) = params.initialization_options {
+        server.update_settings(connection, options)?;
+    }
+    server.main_loop(connection)
+}
+
+struct Server {
+    /// The SourcePawn language, loaded once.
+    language: Language,
+    /// The parser reused across the requests.
+    parser: Parser,
+    /// Content of the documents opened by the client.
+    documents: HashMap<Url, String>,
+    /// Settings sent by the client. If `None`, the settings are read from the
+    /// configuration file of each document, or follow the formatting options of
+    /// the request if there is none.
+    settings: Option<Settings>,
+}
+
+impl Server {
+    fn new() -> anyhow::Result<Self> {
+        let language: Language = tree_sitter_sourcepawn::language().into();
+        let parser = parser::sourcepawn(&language)?;
+
+        Ok(Self {
+            language,
+            parser,
+            documents: HashMap::new(),
+            settings: None,
+        })
+    }
+
+    fn main_loop(&mut self, connection: &Connection) -> anyhow::Result<()> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.handle_request(request);
+                    connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(notification) => {
+                    self.handle_notification(connection, notification)?
+                }
+                Message::Response(_) => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_request(&mut self, request: Request) -> Response {
+        match request.method.as_str() {
+            Formatting::METHOD => self.respond(request, Self::format_document),
+            RangeFormatting::METHOD => self.respond(request, Self::format_document_range),
+            OnTypeFormatting::METHOD => self.respond(request, Self::format_on_type),
+            _ => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("Unhandled method {}.", request.method),
+            ),
+        }
+    }
+
+    /// Deserialize the parameters of a request, and build the response out of
+    /// the result of its handler.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request to respond to.
+    /// * `handler` - The function handling the parameters of the request.
+    fn respond<P, R, F>(&mut self, request: Request, handler: F) -> Response
+    where
+        P: DeserializeOwned,
+        R: Serialize,
+        F: FnOnce(&mut Self, P) -> anyhow::Result<R>,
+    {
+        let params = match serde_json::from_value::<P>(request.params) {
+            Ok(params) => params,
+            Err(err) => {
+                return Response::new_err(
+                    request.id,
+                    ErrorCode::InvalidParams as i32,
+                    err.to_string(),
+                )
+            }
+        };
+        match handler(self, params) {
+            Ok(result) => Response::new_ok(request.id, result),
+            Err(err) => Response::new_err(
+                request.id,
+                ErrorCode::InternalError as i32,
+                format!("{:#}", err),
+            ),
+        }
+    }
+
+    fn handle_notification(
+        &mut self,
+        connection: &Connection,
+        notification: Notification,
+    ) -> anyhow::Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                if let Ok(params) =
+                    serde_json::from_value::<DidOpenTextDocumentParams>(notification.params)
+                {
+                    self.documents
+                        .insert(params.text_document.uri, params.text_document.text);
+                }
+            }
+            DidChangeTextDocument::METHOD => {
+                if let Ok(params) =
+                    serde_json::from_value::<DidChangeTextDocumentParams>(notification.params)
+                {
+                    // The documents are synchronised in full, the last change is the new content.
+                    if let Some(change) = params.content_changes.into_iter().last() {
+                        self.documents.insert(params.text_document.uri, change.text);
+                    }
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                if let Ok(params) =
+                    serde_json::from_value::<DidCloseTextDocumentParams>(notification.params)
+                {
+                    self.documents.remove(&params.text_document.uri);
+                }
+            }
+            DidChangeConfiguration::METHOD => {
+                if let Ok(params) =
+                    serde_json::from_value::<DidChangeConfigurationParams>(notification.params)
+                {
+                    self.update_settings(connection, params.settings)?;
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Update the settings from the configuration sent by the client. The settings
+    /// can be nested under an `sp_format` key. Configurations without this key nor
+    /// any setting of the formatter, e.g. the sections of other tools, are ignored.
+    /// A `null` configuration resets the settings to the configuration files.
+    /// Invalid settings are reported to the user, and the previous settings are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection` - The connection to the client.
+    /// * `value`      - The configuration sent by the client.
+    fn update_settings(
+        &mut self,
+        connection: &Connection,
+        value: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let value = match value.get("sp_format") {
+            Some(nested) => nested.clone(),
+            None if value.is_null() || has_settings(&value) => value,
+            None => return Ok(()),
+        };
+        if value.is_null() {
+            self.settings = None;
+            return Ok(());
+        }
+        match serde_json::from_value::<Settings>(value) {
+            Ok(settings) => self.settings = Some(settings),
+            Err(err) => {
+                let params = ShowMessageParams {
+                    typ: MessageType::ERROR,
+                    message: format!("Invalid settings: {}.", err),
+                };
+                let notification = Notification::new(ShowMessage::METHOD.to_string(), params);
+                connection
+                    .sender
+                    .send(Message::Notification(notification))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn format_document(
+        &mut self,
+        params: DocumentFormattingParams,
+    ) -> anyhow::Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document.uri;
+        let text = self.document(&uri)?;
+        let settings = self.settings_for(&uri, &params.options)?;
+        let formatted =
+            match format_string_parser(&text, &mut self.parser, &self.language, &settings) {
+                Ok(formatted) => formatted,
+                // Leave documents which do not parse untouched.
+                Err(FormatError::Syntax(_)) => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+        if formatted.output == text {
+            return Ok(Some(vec![]));
+        }
+        let range = Range::new(Position::new(0, 0), offset_to_position(&text, text.len()));
+
+        Ok(Some(vec![TextEdit::new(range, formatted.output)]))
+    }
+
+    fn format_document_range(
+        &mut self,
+        params: DocumentRangeFormattingParams,
+    ) -> anyhow::Result<Option<Vec<TextEdit>>> {
+        let text = self.document(&params.text_document.uri)?;
+        let range = position_to_offset(&text, params.range.start)
+            ..position_to_offset(&text, params.range.end);
+
+        self.format_range(&params.text_document.uri, &text, range, &params.options)
+    }
+
+    fn format_on_type(
+        &mut self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> anyhow::Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document_position.text_document.uri;
+        let text = self.document(&uri)?;
+        // Format the declaration or statement the typed character ends.
+        let end = position_to_offset(&text, params.text_document_position.position);
+        let start = end.saturating_sub(params.ch.len());
+
+        self.format_range(&uri, &text, start..end, &params.options)
+    }
+
+    /// Format the nodes covering a range of a document and convert the edits.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri`     - The URI of the document.
+    /// * `text`    - The content of the document.
+    /// * `range`   - The range of bytes to format.
+    /// * `options` - The formatting options of the request.
+    fn format_range(
+        &mut self,
+        uri: &Url,
+        text: &String,
+        range: ops::Range<usize>,
+        options: &FormattingOptions,
+    ) -> anyhow::Result<Option<Vec<TextEdit>>> {
+        let settings = self.settings_for(uri, options)?;
+        let edits =
+            match format_range_parser(text, &mut self.parser, &self.language, range, &settings) {
+                Ok(edits) => edits,
+                // Leave documents which do not parse untouched.
+                Err(FormatError::Syntax(_)) => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+        let edits = edits
+            .into_iter()
+            .map(|edit| {
+                let range = Range::new(
+                    offset_to_position(text, edit.range.start),
+                    offset_to_position(text, edit.range.end),
+                );
+                TextEdit::new(range, edit.new_text)
+            })
+            .collect();
+
+        Ok(Some(edits))
+    }
+
+    fn document(&self, uri: &Url) -> anyhow::Result<String> {
+        self.documents
+            .get(uri)
+            .cloned()
+            .ok_or_else(|| anyhow!("The document {} is not opened.", uri))
+    }
+
+    /// Returns the settings sent by the client, or the settings of the configuration
+    /// file of the document if there are none. Without a configuration file, the
+    /// indentation follows the formatting options of the request.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri`     - The URI of the document to get the settings of.
+    /// * `options` - The formatting options of the request.
+    fn settings_for(&self, uri: &Url, options: &FormattingOptions) -> anyhow::Result<Settings> {
+        if let Some(settings) = &self.settings {
+            return Ok(settings.clone());
+        }
+        let config_path = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| find_config_file(&path));
+        if let Some(config_path) = config_path {
+            return load_config_file(&config_path)
+                .with_context(|| format!("Could not load the settings of {}.", uri));
+        }
+        let mut settings = Settings::default();
+        if options.insert_spaces {
+            settings.indent_style = IndentStyle::Spaces;
+        }
+        if options.tab_size > 0 {
+            settings.indent_width = options.tab_size;
+        }
+
+        Ok(settings)
+    }
+}
+
+/// Returns whether or not a configuration sent by the client has at least one
+/// setting of the formatter.
+///
+/// # Arguments
+///
+/// * `value` - The configuration sent by the client.
+fn has_settings(value: &serde_json::Value) -> bool {
+    let known = serde_json::to_value(Settings::default()).unwrap_or_default();
+    match (value.as_object(), known.as_object()) {
+        (Some(object), Some(known)) => object.keys().any(|key| known.contains_key(key)),
+        _ => false,
+    }
+}
+
+/// Convert an LSP position, whose character is counted in UTF-16 code units,
+/// to a byte offset.
+///
+/// # Arguments
+///
+/// * `text`     - The content of the document.
+/// * `position` - The position to convert.
+fn position_to_offset(text: &str, position: Position) -> usize {
+    let line = usize::try_from(position.line).unwrap();
+    let line_start = line_range_to_byte_range(text, line..line).start;
+    let character = usize::try_from(position.character).unwrap();
+    let mut utf16_column = 0;
+    for (index, c) in text[line_start..].char_indices() {
+        if utf16_column >= character || c == '\n' {
+            return line_start + index;
+        }
+        utf16_column += c.len_utf16();
+    }
+
+    text.len()
+}
+
+/// Convert a byte offset to an LSP position, whose character is counted in
+/// UTF-16 code units.
+///
+/// # Arguments
+///
+/// * `text`   - The content of the document.
+/// * `offset` - The byte offset to convert.
+fn offset_to_position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+
+    Position::new(
+        u32::try_from(line).unwrap(),
+        u32::try_from(character).unwrap(),
+    )
+}
@@ -1,113 +1,774 @@
 #[cfg(not(target_arch = "wasm32"))]
-use std::{fs, str::Utf8Error};
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
-use clap::Parser;
+use clap::{CommandFactory, ErrorKind, Parser, Subcommand};
+use similar::TextDiff;
 
-use sp_format::format_string;
-use sp_format::settings::Settings;
+use sp_format::config::{discover_settings, load_config_file};
+use sp_format::coverage::Coverage;
+use sp_format::diagnostics::Diagnostic;
+use sp_format::error::FormatError;
+use sp_format::settings::{IndentStyle, Settings};
+use sp_format::{format_string_verified, format_string_with_diagnostics, Formatted};
 
 #[cfg(not(target_arch = "wasm32"))]
 /// A tool to format SourcePawn code (new AND old syntaxes).
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Args {
-    /// The file to format.
-    #[clap(value_parser)]
-    file: String,
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// The files to format. Directories are searched recursively for `.sp` and `.inc`
+    /// files, and glob patterns (e.g. `scripting/**/*.sp`) are expanded.
+    /// Pass `-` instead of the files to read the source from stdin and write the result to stdout.
+    #[clap(value_parser, required_unless_present = "stdin_filepath")]
+    files: Vec<String>,
+
+    /// Path of the file being piped through stdin. The file does not have to exist,
+    /// it is used to find the configuration file and in messages. Implies reading from stdin if no files are passed.
+    #[clap(long, value_parser)]
+    stdin_filepath: Option<PathBuf>,
+
+    /// Check if the files are formatted without writing them. Exits with code 1 if
+    /// some files would be reformatted and with code 2 if some files could not be
+    /// parsed or read.
+    #[clap(long, value_parser)]
+    check: bool,
+
+    /// Print a unified diff of the changes instead of writing the files.
+    /// The diff can be applied with `git apply` or `patch -p1`. Implies `--check`.
+    #[clap(long, value_parser)]
+    diff: bool,
+
+    /// Colour the output of `--diff`.
+    #[clap(long, value_parser, requires = "diff")]
+    color: bool,
+
+    /// Treat the diagnostics reported while formatting (e.g. unexpected nodes) as
+    /// errors. Files with diagnostics are not written.
+    #[clap(long, value_parser)]
+    strict: bool,
+
+    /// Do not check that the formatted code has the same tokens as the source.
+    /// By default, files whose formatted code differs are not written.
+    #[clap(long, value_parser)]
+    no_verify: bool,
+
+    /// Path of the configuration file to use. By default, a `.spformat.toml` or
+    /// `spformat.json` file is searched for in the directory of each formatted
+    /// file and its parents. The settings passed as flags override the file.
+    #[clap(long, value_parser)]
+    config: Option<PathBuf>,
 
     /// Number of empty lines to insert before a function declaration.
-    #[clap(long, value_parser, default_value_t = 2)]
-    breaks_before_function_decl: u32,
+    #[clap(long, value_parser)]
+    breaks_before_function_decl: Option<u32>,
 
     /// Number of empty lines to insert before a function definition.
-    #[clap(long, value_parser, default_value_t = 2)]
-    breaks_before_function_def: u32,
+    #[clap(long, value_parser)]
+    breaks_before_function_def: Option<u32>,
 
     /// Number of empty lines to insert before an enum declaration.
-    #[clap(long, value_parser, default_value_t = 2)]
-    breaks_before_enum: u32,
+    #[clap(long, value_parser)]
+    breaks_before_enum: Option<u32>,
 
     /// Number of empty lines to insert before an enum struct declaration.
-    #[clap(long, value_parser, default_value_t = 2)]
-    breaks_before_enum_struct: u32,
+    #[clap(long, value_parser)]
+    breaks_before_enum_struct: Option<u32>,
 
     /// Number of empty lines to insert before a methodmap declaration.
-    #[clap(long, value_parser, default_value_t = 2)]
-    breaks_before_methodmap: u32,
+    #[clap(long, value_parser)]
+    breaks_before_methodmap: Option<u32>,
 
     /// Whether or not to break before a function declaration brace.
-    #[clap(long, value_parser, default_value_t = true)]
-    brace_wrapping_before_function: bool,
+    #[clap(long, value_parser)]
+    brace_wrapping_before_function: Option<bool>,
 
     /// Whether or not to break before a loop statement brace.
-    #[clap(long, value_parser, default_value_t = true)]
-    brace_wrapping_before_loop: bool,
+    #[clap(long, value_parser)]
+    brace_wrapping_before_loop: Option<bool>,
 
     /// Whether or not to break before a condition statement brace.
-    #[clap(long, value_parser, default_value_t = true)]
-    brace_wrapping_before_condition: bool,
+    #[clap(long, value_parser)]
+    brace_wrapping_before_condition: Option<bool>,
 
     /// Whether or not to break before an enum struct declaration brace.
-    #[clap(long, value_parser, default_value_t = true)]
-    brace_wrapping_before_enum_struct: bool,
+    #[clap(long, value_parser)]
+    brace_wrapping_before_enum_struct: Option<bool>,
 
     /// Whether or not to break before an enum declaration brace.
-    #[clap(long, value_parser, default_value_t = true)]
-    brace_wrapping_before_enum: bool,
+    #[clap(long, value_parser)]
+    brace_wrapping_before_enum: Option<bool>,
 
     /// Whether or not to break before a typeset declaration brace.
-    #[clap(long, value_parser, default_value_t = true)]
-    brace_wrapping_before_typeset: bool,
+    #[clap(long, value_parser)]
+    brace_wrapping_before_typeset: Option<bool>,
 
     /// Whether or not to break before a funcenum declaration brace.
-    #[clap(long, value_parser, default_value_t = true)]
-    brace_wrapping_before_funcenum: bool,
+    #[clap(long, value_parser)]
+    brace_wrapping_before_funcenum: Option<bool>,
 
     /// Whether or not to break before a methodmap declaration brace.
-    #[clap(long, value_parser, default_value_t = true)]
-    brace_wrapping_before_methodmap: bool,
+    #[clap(long, value_parser)]
+    brace_wrapping_before_methodmap: Option<bool>,
 
     /// Whether or not to break before a methodmap property declaration brace.
-    #[clap(long, value_parser, default_value_t = true)]
-    brace_wrapping_before_methodmap_property: bool,
-}
-
-#[cfg(not(target_arch = "wasm32"))]
-pub fn build_settings_from_args(args: &Args) -> Settings {
-    let settings = Settings {
-        breaks_before_function_def: args.breaks_before_function_def,
-        breaks_before_function_decl: args.breaks_before_function_decl,
-        breaks_before_enum: args.breaks_before_enum,
-        breaks_before_enum_struct: args.breaks_before_enum_struct,
-        breaks_before_methodmap: args.breaks_before_methodmap,
-        brace_wrapping_before_function: args.brace_wrapping_before_function,
-        brace_wrapping_before_loop: args.brace_wrapping_before_loop,
-        brace_wrapping_before_condition: args.brace_wrapping_before_condition,
-        brace_wrapping_before_enum_struct: args.brace_wrapping_before_enum_struct,
-        brace_wrapping_before_enum: args.brace_wrapping_before_enum,
-        brace_wrapping_before_typeset: args.brace_wrapping_before_typeset,
-        brace_wrapping_before_funcenum: args.brace_wrapping_before_funcenum,
-        brace_wrapping_before_methodmap: args.brace_wrapping_before_methodmap,
-        brace_wrapping_before_methodmap_property: args.brace_wrapping_before_methodmap_property,
+    #[clap(long, value_parser)]
+    brace_wrapping_before_methodmap_property: Option<bool>,
+
+    /// Whether to indent with `tabs` or `spaces`.
+    #[clap(long, value_parser = parse_indent_style)]
+    indent_style: Option<IndentStyle>,
+
+    /// Number of spaces of an indentation level, or width of a tab when aligning code.
+    #[clap(long, value_parser)]
+    indent_width: Option<u32>,
+
+    /// Maximum width of a line before breaking it. `0` disables line breaking.
+    #[clap(long, value_parser)]
+    max_line_width: Option<u32>,
+
+    /// Whether or not to format files containing syntax errors, copying the
+    /// declarations and statements containing errors verbatim.
+    #[clap(long, value_parser)]
+    recover_from_syntax_errors: Option<bool>,
+
+    /// Whether or not to remove the parentheses which do not change the meaning of an
+    /// expression.
+    #[clap(long, value_parser)]
+    remove_redundant_parentheses: Option<bool>,
+
+    /// Whether or not to add parentheses where `&&` and `||`, or bitwise and comparison
+    /// operators, are mixed without them.
+    #[clap(long, value_parser)]
+    add_clarifying_parentheses: Option<bool>,
+
+    /// Whether or not to fill the lines with as many arguments as fit when breaking the
+    /// arguments of a call, instead of writing one argument per line.
+    #[clap(long, value_parser)]
+    bin_pack_arguments: Option<bool>,
+
+    /// Whether or not to fill the lines with as many elements as fit when breaking an
+    /// array literal, instead of writing one element per line.
+    #[clap(long, value_parser)]
+    fill_array_literals: Option<bool>,
+
+    /// Whether or not to end the array literals broken on several lines with a comma.
+    #[clap(long, value_parser)]
+    trailing_comma_in_array_literals: Option<bool>,
+
+    /// Whether or not to right-align the columns of the tables of numbers.
+    #[clap(long, value_parser)]
+    align_array_literal_columns: Option<bool>,
+
+    /// Whether or not to break the chains of `&&` and `||` before the operators, instead
+    /// of after them.
+    #[clap(long, value_parser)]
+    break_before_logical_operators: Option<bool>,
+
+    /// Whether or not to align the names of the arguments in a column when the argument
+    /// declarations of a function are broken one per line.
+    #[clap(long, value_parser)]
+    align_argument_names: Option<bool>,
+
+    /// Whether or not to convert the old syntax variable declarations to the new syntax,
+    /// e.g. `new Float:x` to `float x`, and the functags and funcenums to typedefs and
+    /// typesets.
+    #[clap(long, value_parser)]
+    convert_old_syntax: Option<bool>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run a language server speaking JSON-RPC over stdio, which provides document,
+    /// range and on type formatting.
+    Lsp,
+    /// Format a corpus of files and report which node kinds of the grammar are
+    /// formatted, copied verbatim or reported as unexpected by the writers.
+    Coverage {
+        /// The files of the corpus. Directories are searched recursively for `.sp`
+        /// and `.inc` files, and glob patterns are expanded.
+        #[clap(value_parser, required = true)]
+        files: Vec<String>,
+    },
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_indent_style(value: &str) -> Result<IndentStyle, String> {
+    match value {
+        "tabs" => Ok(IndentStyle::Tabs),
+        "spaces" => Ok(IndentStyle::Spaces),
+        _ => Err(format!("expected `tabs` or `spaces`, got `{}`", value)),
+    }
+}
+
+/// Override the fields of the settings which were passed as flags.
+#[cfg(not(target_arch = "wasm32"))]
+macro_rules! override_settings {
+    ($settings:ident, $args:ident, $($field:ident),* $(,)?) => {
+        $(
+            if let Some(value) = $args.$field {
+                $settings.$field = value;
+            }
+        )*
+    };
+}
+
+/// Build the settings to format a file with. The settings are read from the
+/// configuration file passed with `--config`, or from the configuration file
+/// found by walking up from the file. The flags passed to the CLI take precedence.
+///
+/// # Arguments
+///
+/// * `args` - The arguments passed to the CLI.
+/// * `path` - The path of the file to format.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn build_settings(args: &Args, path: &Path) -> anyhow::Result<Settings> {
+    let mut settings = match &args.config {
+        Some(config_path) => load_config_file(config_path)?,
+        None => discover_settings(path)?,
+    };
+
+    override_settings!(
+        settings,
+        args,
+        breaks_before_function_decl,
+        breaks_before_function_def,
+        breaks_before_enum,
+        breaks_before_enum_struct,
+        breaks_before_methodmap,
+        brace_wrapping_before_function,
+        brace_wrapping_before_loop,
+        brace_wrapping_before_condition,
+        brace_wrapping_before_enum_struct,
+        brace_wrapping_before_enum,
+        brace_wrapping_before_typeset,
+        brace_wrapping_before_funcenum,
+        brace_wrapping_before_methodmap,
+        brace_wrapping_before_methodmap_property,
+        indent_style,
+        indent_width,
+        max_line_width,
+        recover_from_syntax_errors,
+        remove_redundant_parentheses,
+        add_clarifying_parentheses,
+        bin_pack_arguments,
+        fill_array_literals,
+        trailing_comma_in_array_literals,
+        align_array_literal_columns,
+        break_before_logical_operators,
+        align_argument_names,
+        convert_old_syntax,
+    );
+
+    Ok(settings)
+}
+
+/// Extensions of the files which are formatted when walking a directory.
+#[cfg(not(target_arch = "wasm32"))]
+const SOURCEPAWN_EXTENSIONS: [&str; 2] = ["sp", "inc"];
+
+/// What to do with the formatted output of a file.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Write the output to the file.
+    Write,
+    /// Only check if the file is formatted.
+    Check,
+    /// Print a unified diff between the file and the output.
+    Diff { color: bool },
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Mode {
+    fn from_args(args: &Args) -> Self {
+        if args.diff {
+            Mode::Diff { color: args.color }
+        } else if args.check {
+            Mode::Check
+        } else {
+            Mode::Write
+        }
+    }
+
+    fn writes(&self) -> bool {
+        *self == Mode::Write
+    }
+}
+
+/// Outcome of the formatting of a batch of files.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Default)]
+struct Summary {
+    changed: Vec<PathBuf>,
+    unchanged: Vec<PathBuf>,
+    skipped: Vec<(PathBuf, String)>,
+    failed: Vec<(PathBuf, String)>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Summary {
+    /// Print the summary to stderr, stdout is kept for the diffs.
+    fn print(&self, mode: Mode) {
+        if !self.changed.is_empty() {
+            if !mode.writes() {
+                eprintln!("{} file(s) would be reformatted:", self.changed.len());
+            } else {
+                eprintln!("Formatted {} file(s):", self.changed.len());
+            }
+            for path in self.changed.iter() {
+                eprintln!("  {}", path.display());
+            }
+        }
+        if !self.skipped.is_empty() {
+            eprintln!(
+                "{} file(s) could not be parsed because of syntax errors:",
+                self.skipped.len()
+            );
+            for (path, error) in self.skipped.iter() {
+                eprintln!("  {}: {}", path.display(), error);
+            }
+        }
+        if !self.failed.is_empty() {
+            eprintln!("Failed to process {} file(s):", self.failed.len());
+            for (path, error) in self.failed.iter() {
+                eprintln!("  {}: {}", path.display(), error);
+            }
+        }
+        eprintln!(
+            "{} changed, {} unchanged, {} skipped, {} failed.",
+            self.changed.len(),
+            self.unchanged.len(),
+            self.skipped.len(),
+            self.failed.len()
+        );
+    }
+}
+
+/// Returns true if the path has a SourcePawn extension.
+///
+/// # Arguments
+///
+/// * `path` - The path to check.
+#[cfg(not(target_arch = "wasm32"))]
+fn is_sourcepawn_file(path: &Path) -> bool {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => SOURCEPAWN_EXTENSIONS.contains(&extension),
+        None => false,
+    }
+}
+
+/// Recursively collect the SourcePawn files of a directory.
+///
+/// # Arguments
+///
+/// * `directory` - The directory to walk.
+/// * `files`     - The vector to push the files into.
+#[cfg(not(target_arch = "wasm32"))]
+fn collect_directory(directory: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries = fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    // Sort the entries to get a stable output across platforms.
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_directory(&path, files)?;
+        } else if is_sourcepawn_file(&path) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Expand the paths passed to the CLI into a list of files to format.
+/// Files are kept as is, directories are walked recursively and anything
+/// else is treated as a glob pattern.
+///
+/// # Arguments
+///
+/// * `patterns` - The paths, directories and glob patterns to expand.
+/// * `summary`  - The summary to record the patterns which could not be expanded in.
+#[cfg(not(target_arch = "wasm32"))]
+fn collect_files(patterns: &[String], summary: &mut Summary) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for pattern in patterns {
+        let path = Path::new(pattern);
+        if path.is_file() {
+            files.push(path.to_path_buf());
+            continue;
+        }
+        if path.is_dir() {
+            if let Err(err) = collect_directory(path, &mut files) {
+                summary.failed.push((path.to_path_buf(), err.to_string()));
+            }
+            continue;
+        }
+        let entries = match glob::glob(pattern) {
+            Ok(entries) => entries,
+            Err(err) => {
+                summary.failed.push((path.to_path_buf(), err.to_string()));
+                continue;
+            }
+        };
+        let mut matched = false;
+        for entry in entries {
+            matched = true;
+            match entry {
+                Ok(entry) => {
+                    if entry.is_dir() {
+                        if let Err(err) = collect_directory(&entry, &mut files) {
+                            summary.failed.push((entry, err.to_string()));
+                        }
+                    } else {
+                        files.push(entry);
+                    }
+                }
+                Err(err) => summary
+                    .failed
+                    .push((err.path().to_path_buf(), err.to_string())),
+            }
+        }
+        if !matched {
+            summary
+                .failed
+                .push((path.to_path_buf(), "No such file or directory.".to_string()));
+        }
+    }
+
+    // A file can be matched by several patterns, only format it once.
+    let mut seen = std::collections::HashSet::new();
+    files.retain(|file| seen.insert(file.clone()));
+
+    files
+}
+
+/// Print a unified diff between the source of a file and its formatted output.
+///
+/// # Arguments
+///
+/// * `path`   - The path of the file, used in the diff headers.
+/// * `source` - The original source of the file.
+/// * `output` - The formatted source of the file.
+/// * `color`  - Whether or not to colour the diff.
+#[cfg(not(target_arch = "wasm32"))]
+fn print_diff(path: &Path, source: &str, output: &str, color: bool) {
+    // Make the path relative to the working directory, and use forward slashes, so
+    // the patch can be applied from there on any platform.
+    let path = env::current_dir()
+        .ok()
+        .and_then(|dir| path.strip_prefix(dir).ok())
+        .unwrap_or(path);
+    let path = path.to_string_lossy().replace('\\', "/");
+    let path = path.trim_start_matches("./").trim_start_matches('/');
+    let diff = TextDiff::from_lines(source, output);
+    let patch = diff
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{}", path), &format!("b/{}", path))
+        .to_string();
+    if !color {
+        print!("{}", patch);
+        return;
+    }
+    for line in patch.split_inclusive('\n') {
+        let style = if line.starts_with("---") || line.starts_with("+++") {
+            "\x1b[1m"
+        } else if line.starts_with("@@") {
+            "\x1b[36m"
+        } else if line.starts_with('-') {
+            "\x1b[31m"
+        } else if line.starts_with('+') {
+            "\x1b[32m"
+        } else {
+            print!("{}", line);
+            continue;
+        };
+        println!("{}{}\x1b[0m", style, line.trim_end_matches('\n'));
+    }
+}
+
+/// Print the diagnostics reported while formatting a file to stderr.
+///
+/// # Arguments
+///
+/// * `path`        - The path of the file the diagnostics are about.
+/// * `diagnostics` - The diagnostics to print.
+#[cfg(not(target_arch = "wasm32"))]
+fn print_diagnostics(path: &Path, diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        eprintln!("{}:{}", path.display(), diagnostic);
+    }
+}
+
+/// Format a file and record the outcome in the summary.
+///
+/// # Arguments
+///
+/// * `path`    - The path of the file to format.
+/// * `args`    - The arguments passed to the CLI.
+/// * `mode`    - What to do with the formatted output.
+/// * `summary` - The summary to record the outcome in.
+#[cfg(not(target_arch = "wasm32"))]
+fn format_file(path: &Path, args: &Args, mode: Mode, summary: &mut Summary) {
+    let settings = match build_settings(args, path) {
+        Ok(settings) => settings,
+        Err(err) => {
+            summary
+                .failed
+                .push((path.to_path_buf(), format!("{:#}", err)));
+            return;
+        }
+    };
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            summary.failed.push((path.to_path_buf(), err.to_string()));
+            return;
+        }
+    };
+    let output = match format_source(&source, settings, args) {
+        Ok(formatted) => {
+            print_diagnostics(path, &formatted.diagnostics);
+            if args.strict && !formatted.diagnostics.is_empty() {
+                summary.failed.push((
+                    path.to_path_buf(),
+                    format!("{} diagnostic(s) reported.", formatted.diagnostics.len()),
+                ));
+                return;
+            }
+            formatted.output
+        }
+        Err(err @ FormatError::Syntax(_)) => {
+            // The file has a syntax error, don't write to it.
+            summary.skipped.push((path.to_path_buf(), err.to_string()));
+            return;
+        }
+        Err(err) => {
+            summary.failed.push((path.to_path_buf(), err.to_string()));
+            return;
+        }
     };
+    if output == source {
+        summary.unchanged.push(path.to_path_buf());
+        return;
+    }
+    match mode {
+        Mode::Write => (),
+        Mode::Check => {
+            summary.changed.push(path.to_path_buf());
+            return;
+        }
+        Mode::Diff { color } => {
+            print_diff(path, &source, &output, color);
+            summary.changed.push(path.to_path_buf());
+            return;
+        }
+    }
+    match fs::write(path, output) {
+        Ok(_) => summary.changed.push(path.to_path_buf()),
+        Err(err) => summary.failed.push((path.to_path_buf(), err.to_string())),
+    }
+}
 
-    return settings;
+/// Format a source, and verify the output unless `--no-verify` was passed.
+///
+/// # Arguments
+///
+/// * `source`   - The source to format.
+/// * `settings` - The settings to format the source with.
+/// * `args`     - The arguments passed to the CLI.
+#[cfg(not(target_arch = "wasm32"))]
+fn format_source(
+    source: &String,
+    settings: Settings,
+    args: &Args,
+) -> Result<Formatted, FormatError> {
+    if args.no_verify {
+        format_string_with_diagnostics(source, settings)
+    } else {
+        format_string_verified(source, settings)
+    }
+}
+
+/// Format the source read from stdin and write the result to stdout.
+/// If the source cannot be formatted, it is written back unchanged so that
+/// editors piping their buffer through the formatter do not lose it.
+///
+/// # Arguments
+///
+/// * `args` - The arguments passed to the CLI.
+/// * `mode` - What to do with the formatted output.
+#[cfg(not(target_arch = "wasm32"))]
+fn format_stdin(args: &Args, mode: Mode) -> ExitCode {
+    let path = args
+        .stdin_filepath
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("<stdin>"));
+
+    let mut source = String::new();
+    if let Err(err) = io::stdin().read_to_string(&mut source) {
+        eprintln!("{}: {}", path.display(), err);
+        return ExitCode::from(2);
+    }
+    // Without a hint, discover the configuration from the working directory.
+    let settings_path = args
+        .stdin_filepath
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("."));
+    let settings = match build_settings(args, &settings_path) {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("{}: {:#}", path.display(), err);
+            if mode.writes() {
+                print!("{}", source);
+            }
+            return ExitCode::from(2);
+        }
+    };
+    let output = match format_source(&source, settings, args) {
+        Ok(formatted) if args.strict && !formatted.diagnostics.is_empty() => {
+            print_diagnostics(&path, &formatted.diagnostics);
+            if mode.writes() {
+                print!("{}", source);
+            }
+            return ExitCode::from(2);
+        }
+        Ok(formatted) => {
+            print_diagnostics(&path, &formatted.diagnostics);
+            formatted.output
+        }
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            if mode.writes() {
+                print!("{}", source);
+            }
+            return ExitCode::from(2);
+        }
+    };
+
+    match mode {
+        Mode::Write => {
+            let mut stdout = io::stdout();
+            if let Err(err) = stdout
+                .write_all(output.as_bytes())
+                .and_then(|_| stdout.flush())
+            {
+                eprintln!("{}: {}", path.display(), err);
+                return ExitCode::from(2);
+            }
+        }
+        Mode::Check => {
+            if output != source {
+                eprintln!("{} would be reformatted.", path.display());
+                return ExitCode::from(1);
+            }
+        }
+        Mode::Diff { color } => {
+            if output != source {
+                print_diff(&path, &source, &output, color);
+                return ExitCode::from(1);
+            }
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Format a corpus of files and print the coverage of the node kinds of the
+/// grammar to stdout. The files which could not be formatted are left out of
+/// the report and listed on stderr.
+///
+/// # Arguments
+///
+/// * `patterns` - The paths, directories and glob patterns of the corpus.
+#[cfg(not(target_arch = "wasm32"))]
+fn report_coverage(patterns: &[String]) -> ExitCode {
+    let mut summary = Summary::default();
+    let files = collect_files(patterns, &mut summary);
+    let mut coverage = Coverage::new();
+    for file in files.iter() {
+        let result = discover_settings(file)
+            .map_err(|err| format!("{:#}", err))
+            .and_then(|settings| {
+                let source = fs::read_to_string(file).map_err(|err| err.to_string())?;
+                coverage
+                    .add_source(&source, &settings)
+                    .map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            summary.failed.push((file.to_path_buf(), err));
+        }
+    }
+    print!("{}", coverage);
+
+    if !summary.failed.is_empty() {
+        eprintln!("Failed to process {} file(s):", summary.failed.len());
+        for (path, error) in summary.failed.iter() {
+            eprintln!("  {}: {}", path.display(), error);
+        }
+        return ExitCode::from(2);
+    }
+
+    ExitCode::SUCCESS
 }
 
 #[allow(dead_code)]
 #[cfg(not(target_arch = "wasm32"))]
-fn main() -> Result<(), Utf8Error> {
+fn main() -> ExitCode {
     let args = Args::parse();
+    match &args.command {
+        Some(Command::Lsp) => {
+            return match sp_format::lsp::run() {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(err) => {
+                    eprintln!("{:#}", err);
+                    ExitCode::from(2)
+                }
+            };
+        }
+        Some(Command::Coverage { files }) => return report_coverage(files),
+        None => (),
+    }
+    let mode = Mode::from_args(&args);
 
-    let settings = build_settings_from_args(&args);
-    let filename = args.file;
-    let source =
-        fs::read_to_string(&filename).expect("Something went wrong while reading the file.");
-    let output = format_string(&source, settings).unwrap();
-    if output.len() == 0 && source.trim().len() > 0 {
-        // An error occured, don't write to the file.
-        return Ok(());
+    if args.files.len() > 1 && args.files.iter().any(|file| file == "-") {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "`-` reads the source from stdin and cannot be passed with other files",
+            )
+            .exit();
     }
-    fs::write(&filename, output).expect("Something went wrong while writing the file.");
-    Ok(())
+    if (args.files.is_empty() && args.stdin_filepath.is_some())
+        || (args.files.len() == 1 && args.files[0] == "-")
+    {
+        return format_stdin(&args, mode);
+    }
+
+    let mut summary = Summary::default();
+    let files = collect_files(&args.files, &mut summary);
+    for file in files.iter() {
+        format_file(file, &args, mode, &mut summary);
+    }
+    summary.print(mode);
+
+    if !summary.failed.is_empty() || (!mode.writes() && !summary.skipped.is_empty()) {
+        return ExitCode::from(2);
+    }
+    if !mode.writes() && !summary.changed.is_empty() {
+        return ExitCode::from(1);
+    }
+
+    ExitCode::SUCCESS
 }
@@ -1,5 +1,10 @@
-pub fn sourcepawn(language: &tree_sitter::Language) -> anyhow::Result<tree_sitter::Parser> {
-    let mut parser = tree_sitter::Parser::new()?;
-    parser.set_language(language)?;
+use crate::error::FormatError;
+
+pub fn sourcepawn(language: &tree_sitter::Language) -> Result<tree_sitter::Parser, FormatError> {
+    let mut parser =
+        tree_sitter::Parser::new().map_err(|err| FormatError::Parser(err.to_string()))?;
+    parser
+        .set_language(language)
+        .map_err(|err| FormatError::Parser(err.to_string()))?;
     Ok(parser)
 }
@@ -1,19 +1,133 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize)]
+/// Character used to indent the code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndentStyle {
+    Tabs,
+    Spaces,
+}
+
+/// Settings of the formatter.
+///
+/// Every field is optional when deserializing, missing fields fall back to
+/// the default value documented on the field.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
+    /// Number of empty lines to insert before a function declaration. Defaults to `2`.
     pub breaks_before_function_decl: u32,
+    /// Number of empty lines to insert before a function definition. Defaults to `2`.
     pub breaks_before_function_def: u32,
+    /// Number of empty lines to insert before an enum declaration. Defaults to `2`.
     pub breaks_before_enum: u32,
+    /// Number of empty lines to insert before an enum struct declaration. Defaults to `2`.
     pub breaks_before_enum_struct: u32,
+    /// Number of empty lines to insert before a methodmap declaration. Defaults to `2`.
     pub breaks_before_methodmap: u32,
+    /// Whether or not to break before a function declaration brace. Defaults to `true`.
     pub brace_wrapping_before_function: bool,
+    /// Whether or not to break before a loop statement brace. Defaults to `true`.
     pub brace_wrapping_before_loop: bool,
+    /// Whether or not to break before a condition statement brace. Defaults to `true`.
     pub brace_wrapping_before_condition: bool,
+    /// Whether or not to break before an enum struct declaration brace. Defaults to `true`.
     pub brace_wrapping_before_enum_struct: bool,
+    /// Whether or not to break before an enum declaration brace. Defaults to `true`.
     pub brace_wrapping_before_enum: bool,
+    /// Whether or not to break before a typeset declaration brace. Defaults to `true`.
     pub brace_wrapping_before_typeset: bool,
+    /// Whether or not to break before a funcenum declaration brace. Defaults to `true`.
     pub brace_wrapping_before_funcenum: bool,
+    /// Whether or not to break before a methodmap declaration brace. Defaults to `true`.
     pub brace_wrapping_before_methodmap: bool,
+    /// Whether or not to break before a methodmap property declaration brace. Defaults to `true`.
     pub brace_wrapping_before_methodmap_property: bool,
+    /// Whether to indent with tabs or spaces. Defaults to `tabs`.
+    pub indent_style: IndentStyle,
+    /// Number of columns of an indentation level. This is the number of spaces
+    /// of an indent, or the width of a tab when aligning code. Defaults to `4`.
+    pub indent_width: u32,
+    /// Maximum width of a line. Longer expressions and declarations are broken
+    /// on several lines. `0` disables line breaking. Defaults to `80`.
+    pub max_line_width: u32,
+    /// Whether or not to format files containing syntax errors. The declarations
+    /// and statements containing errors are copied verbatim. Defaults to `false`.
+    pub recover_from_syntax_errors: bool,
+    /// Whether or not to remove the parentheses which do not change the meaning of an
+    /// expression, e.g. around a literal, in `return (x);` or in `((a + b))`. Defaults to `false`.
+    pub remove_redundant_parentheses: bool,
+    /// Whether or not to add parentheses around the operands of a binary expression which
+    /// mix operators in a way which is easy to misread, like `a && b || c` or `x & MASK == 0`.
+    /// Defaults to `false`.
+    pub add_clarifying_parentheses: bool,
+    /// Whether or not to fill the lines with as many arguments as fit when the arguments
+    /// of a call do not fit on one line, instead of writing one argument per line. Defaults to `false`.
+    pub bin_pack_arguments: bool,
+    /// Whether or not to fill the lines with as many elements as fit when an array literal
+    /// does not fit on one line, instead of writing one element per line. Defaults to `false`.
+    pub fill_array_literals: bool,
+    /// Whether or not to end the array literals broken on several lines with a comma,
+    /// and to remove it from the array literals which fit on one line. Otherwise the
+    /// trailing commas are kept as they are. Defaults to `false`.
+    pub trailing_comma_in_array_literals: bool,
+    /// Whether or not to right-align the columns of the tables of numbers, i.e. array
+    /// literals of array literals of numbers, when they are broken one row per line.
+    /// Defaults to `false`.
+    pub align_array_literal_columns: bool,
+    /// Whether or not to break the chains of `&&` and `||` which do not fit on one line
+    /// before the operators, instead of after them. Defaults to `false`.
+    pub break_before_logical_operators: bool,
+    /// Whether or not to align the names of the arguments in a column when the argument
+    /// declarations of a function are broken one per line. Defaults to `false`.
+    pub align_argument_names: bool,
+    /// Whether or not to convert the old syntax variable declarations to the new syntax,
+    /// e.g. `new Float:x` to `float x`, and the functags and funcenums to typedefs and
+    /// typesets. The declarations which cannot be converted safely are kept as is and
+    /// reported. Defaults to `false`.
+    pub convert_old_syntax: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            breaks_before_function_decl: 2,
+            breaks_before_function_def: 2,
+            breaks_before_enum: 2,
+            breaks_before_enum_struct: 2,
+            breaks_before_methodmap: 2,
+            brace_wrapping_before_function: true,
+            brace_wrapping_before_loop: true,
+            brace_wrapping_before_condition: true,
+            brace_wrapping_before_enum_struct: true,
+            brace_wrapping_before_enum: true,
+            brace_wrapping_before_typeset: true,
+            brace_wrapping_before_funcenum: true,
+            brace_wrapping_before_methodmap: true,
+            brace_wrapping_before_methodmap_property: true,
+            indent_style: IndentStyle::Tabs,
+            indent_width: 4,
+            max_line_width: 80,
+            recover_from_syntax_errors: false,
+            remove_redundant_parentheses: false,
+            add_clarifying_parentheses: false,
+            bin_pack_arguments: false,
+            fill_array_literals: false,
+            trailing_comma_in_array_literals: false,
+            align_array_literal_columns: false,
+            break_before_logical_operators: false,
+            align_argument_names: false,
+            convert_old_syntax: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Returns the string of one indentation level.
+    pub fn indent_string(&self) -> String {
+        match self.indent_style {
+            IndentStyle::Tabs => "\t".to_string(),
+            IndentStyle::Spaces => " ".repeat(self.indent_width as usize),
+        }
+    }
 }
@@ -0,0 +1,568 @@
+//! Safety check run after formatting, which makes sure the formatter only
+//! changed the whitespaces of the code.
+//!
+//! Both the input and the output are parsed, and their leaf tokens are
+//! compared in order. The comments are compared separately, so that they
+//! can be moved around by the formatter. When the settings allow the formatter
+//! to add or remove parentheses, the parentheses of parenthesized expressions are
+//! left out, and the bounds of the operations are compared instead, so that a
+//! change of the grouping is still caught. When the old syntax declarations are
+//! converted to the new syntax, the tokens of both sources are read in a common
+//! form: the old tags are read as the types they are converted to, the type of a
+//! declaration is repeated before each of its variables, and the functags and
+//! funcenums are read as the typedefs and typesets they are converted to.
+
+use std::{borrow::Borrow, str::Utf8Error};
+
+use tree_sitter::{Node, Parser};
+
+use crate::{
+    error::FormatError, formatter::parse, settings::Settings,
+    writers::old_variables::convert_old_tag,
+};
+
+/// Kinds of the nodes which are compared as a whole, whitespaces included.
+const ATOMIC_KINDS: [&str; 2] = ["string_literal", "char_literal"];
+
+/// Kinds of the operations whose bounds are compared when the parentheses are
+/// left out.
+const OPERATION_KINDS: [&str; 7] = [
+    "assignment_expression",
+    "binary_expression",
+    "comma_expression",
+    "old_type_cast",
+    "ternary_expression",
+    "unary_expression",
+    "update_expression",
+];
+
+/// Kinds of the variable declarations, in the new and the old syntax.
+const DECLARATION_KINDS: [&str; 4] = [
+    "global_variable_declaration",
+    "old_global_variable_declaration",
+    "old_variable_declaration_statement",
+    "variable_declaration_statement",
+];
+
+/// Kinds of the nodes whose writer leaves out the semicolons of the source and
+/// writes its own, so that a missing semicolon is added.
+const TERMINATED_KINDS: [&str; 22] = [
+    "assertion",
+    "break_statement",
+    "continue_statement",
+    "funcenum",
+    "functag",
+    "global_variable_declaration",
+    "hardcoded_symbol",
+    "methodmap",
+    "methodmap_alias",
+    "methodmap_native",
+    "methodmap_native_constructor",
+    "methodmap_native_destructor",
+    "methodmap_property",
+    "methodmap_property_alias",
+    "methodmap_property_setter",
+    "old_global_variable_declaration",
+    "old_variable_declaration_statement",
+    "struct_constructor",
+    "struct_declaration",
+    "typedef",
+    "typeset",
+    "variable_declaration_statement",
+];
+
+/// A token of a source.
+#[derive(Clone)]
+struct Token {
+    /// The text of the token. The whitespaces are removed, unless the token
+    /// is one of the [`ATOMIC_KINDS`].
+    text: String,
+    /// Zero-based row of the start of the token.
+    row: u32,
+    /// Zero-based column of the start of the token.
+    column: u32,
+}
+
+impl Token {
+    fn new(node: &Node, source: &[u8]) -> Result<Self, Utf8Error> {
+        let text = node.utf8_text(source)?;
+        let text = if ATOMIC_KINDS.contains(&node.kind().borrow()) {
+            text.trim().to_string()
+        } else {
+            text.split_whitespace().collect()
+        };
+
+        Ok(Self {
+            text,
+            row: node.start_position().row(),
+            column: node.start_position().column(),
+        })
+    }
+
+    /// Build a token which is not part of the source, like the implicit `int`
+    /// type of an untagged old syntax declaration.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The node the token is read at.
+    /// * `text` - The text of the token.
+    fn implicit(node: &Node, text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            row: node.start_position().row(),
+            column: node.start_position().column(),
+        }
+    }
+
+    /// Build a token marking the start or the end of an operation, which is not
+    /// part of the source. The operations of the input and of the output must
+    /// have the same bounds for the grouping of their operands to be the same.
+    ///
+    /// # Arguments
+    ///
+    /// * `node`   - The operation node.
+    /// * `prefix` - `<` for the start of the operation, `</` for its end.
+    fn bound(node: &Node, prefix: &str) -> Self {
+        Self {
+            text: format!("{}{}>", prefix, node.kind()),
+            row: node.start_position().row(),
+            column: node.start_position().column(),
+        }
+    }
+}
+
+/// What to leave out when collecting the tokens of a source.
+struct CollectOptions {
+    /// Whether or not to leave out the parentheses of parenthesized expressions,
+    /// and to mark the bounds of the [`OPERATION_KINDS`] instead.
+    skip_parentheses: bool,
+    /// Whether or not to read the declarations in the form they have once
+    /// converted to the new syntax.
+    convert_old_syntax: bool,
+    /// Whether or not to leave out the trailing commas of the array literals.
+    skip_array_trailing_commas: bool,
+}
+
+/// The tokens and the comments of a source.
+#[derive(Default)]
+struct Tokens {
+    tokens: Vec<Token>,
+    comments: Vec<Token>,
+}
+
+/// Check that the formatted output has the same tokens and comments as the
+/// input, in the same order.
+///
+/// # Arguments
+///
+/// * `input`    - The source which was formatted.
+/// * `output`   - The formatted source.
+/// * `parser`   - The parser of the SourcePawn language.
+/// * `settings` - The settings the source was formatted with.
+pub fn verify_output(
+    input: &String,
+    output: &String,
+    parser: &mut Parser,
+    settings: &Settings,
+) -> Result<(), FormatError> {
+    let input_tree = parse(input, parser)?;
+    let output_tree = parse(output, parser)?;
+    if output_tree.root_node().has_error() && !input_tree.root_node().has_error() {
+        return Err(FormatError::Verification(
+            "it has syntax errors".to_string(),
+        ));
+    }
+    let options = CollectOptions {
+        skip_parentheses: settings.remove_redundant_parentheses
+            || settings.add_clarifying_parentheses,
+        convert_old_syntax: settings.convert_old_syntax,
+        skip_array_trailing_commas: settings.trailing_comma_in_array_literals,
+    };
+    let input_tokens = collect_tokens(&input_tree.root_node(), input.as_bytes(), &options)?;
+    let output_tokens = collect_tokens(&output_tree.root_node(), output.as_bytes(), &options)?;
+
+    compare_tokens(&input_tokens.tokens, &output_tokens.tokens, "token")?;
+    compare_tokens(&input_tokens.comments, &output_tokens.comments, "comment")
+}
+
+/// Collect the tokens and the comments of a tree. The tokens the formatter may
+/// add or remove are left out, see [`is_optional_token`].
+///
+/// # Arguments
+///
+/// * `root`    - The root of the tree.
+/// * `source`  - The source of the tree.
+/// * `options` - What else to leave out.
+fn collect_tokens(
+    root: &Node,
+    source: &[u8],
+    options: &CollectOptions,
+) -> Result<Tokens, Utf8Error> {
+    let mut tokens = Tokens::default();
+    collect_node_tokens(root, source, options, &mut tokens)?;
+
+    Ok(tokens)
+}
+
+/// Returns whether or not a token may be added or removed by the formatter: the
+/// semicolons ending the [`TERMINATED_KINDS`], the trailing commas of the enums and
+/// of the struct constructors, and the trailing commas of the array literals when
+/// `trailing_comma_in_array_literals` is set.
+///
+/// # Arguments
+///
+/// * `node`    - The node of the token.
+/// * `options` - What else to leave out.
+fn is_optional_token(node: &Node, options: &CollectOptions) -> bool {
+    let parent = match node.parent() {
+        Some(parent) => parent,
+        None => return false,
+    };
+    match node.kind().borrow() {
+        ";" => TERMINATED_KINDS.contains(&parent.kind().borrow()),
+        "," => {
+            let trailing_commas = match parent.kind().borrow() {
+                // Every entry, and every field of a struct constructor, is followed by a comma.
+                "enum_entries" | "struct_constructor" => true,
+                "array_literal" => options.skip_array_trailing_commas,
+                _ => false,
+            };
+            let mut next = node.next_sibling();
+            while let Some(sibling) = next.filter(|sibling| sibling.kind() == "comment") {
+                next = sibling.next_sibling();
+            }
+            trailing_commas && next.map_or(false, |sibling| sibling.kind() == "}")
+        }
+        _ => false,
+    }
+}
+
+fn collect_node_tokens(
+    node: &Node,
+    source: &[u8],
+    options: &CollectOptions,
+    tokens: &mut Tokens,
+) -> Result<(), Utf8Error> {
+    if node.kind() == "comment" {
+        tokens.comments.push(Token::new(node, source)?);
+        return Ok(());
+    }
+    if options.convert_old_syntax {
+        match node.kind().borrow() {
+            kind if DECLARATION_KINDS.contains(&kind) => {
+                return collect_declaration_tokens(node, source, options, tokens)
+            }
+            "new" | "decl" => return Ok(()),
+            "old_type" => {
+                tokens.tokens.push(converted_tag(node, source)?);
+                return Ok(());
+            }
+            "argument_type" => return collect_argument_type_tokens(node, source, options, tokens),
+            "functag" => return collect_functag_tokens(node, source, options, tokens),
+            "funcenum" => return collect_funcenum_tokens(node, source, options, tokens),
+            _ => (),
+        }
+    }
+    if node.child_count() == 0 || ATOMIC_KINDS.contains(&node.kind().borrow()) {
+        if is_optional_token(node, options) {
+            return Ok(());
+        }
+        let token = Token::new(node, source)?;
+        // Skip the `MISSING` nodes, which are empty.
+        if !token.text.is_empty() {
+            tokens.tokens.push(token);
+        }
+        return Ok(());
+    }
+    let skip_parentheses = options.skip_parentheses && node.kind() == "parenthesized_expression";
+    let mark_bounds = options.skip_parentheses && OPERATION_KINDS.contains(&node.kind().borrow());
+    if mark_bounds {
+        tokens.tokens.push(Token::bound(node, "<"));
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if skip_parentheses && (child.kind() == "(" || child.kind() == ")") {
+            continue;
+        }
+        collect_node_tokens(&child, source, options, tokens)?;
+    }
+    if mark_bounds {
+        tokens.tokens.push(Token::bound(node, "</"));
+    }
+
+    Ok(())
+}
+
+/// Collect the tokens of a variable declaration, in the new or the old syntax,
+/// in the form they have once converted: the type, or the converted tag, is
+/// collected before each variable, the untagged variables are `int`s, and the
+/// `Handle` variables initialized to `INVALID_HANDLE` are initialized to `null`.
+///
+/// # Arguments
+///
+/// * `node`    - The variable declaration node.
+/// * `source`  - The source of the tree.
+/// * `options` - What else to leave out.
+/// * `tokens`  - The tokens to push into.
+fn collect_declaration_tokens(
+    node: &Node,
+    source: &[u8],
+    options: &CollectOptions,
+    tokens: &mut Tokens,
+) -> Result<(), Utf8Error> {
+    // The type of a new syntax declaration, with its dimensions (`int[]`).
+    let mut type_tokens = Tokens::default();
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind().borrow() {
+            "type" | "dimension" => collect_node_tokens(&child, source, options, &mut type_tokens)?,
+            "variable_declaration" => {
+                tokens.tokens.extend(type_tokens.tokens.iter().cloned());
+                collect_node_tokens(&child, source, options, tokens)?;
+            }
+            "old_variable_declaration" => {
+                let mut sub_cursor = child.walk();
+                let tag = child
+                    .children(&mut sub_cursor)
+                    .find(|sub_child| sub_child.kind() == "old_type");
+                let type_ = match tag {
+                    Some(tag) => converted_tag(&tag, source)?,
+                    None => Token::implicit(&child, "int"),
+                };
+                let is_handle = type_.text == "Handle";
+                tokens.tokens.push(type_);
+                for sub_child in child.children(&mut sub_cursor) {
+                    match sub_child.kind().borrow() {
+                        "old_type" => continue,
+                        "symbol"
+                            if is_handle
+                                && sub_child
+                                    .prev_sibling()
+                                    .map_or(false, |prev| prev.kind() == "=")
+                                && sub_child.utf8_text(source)? == "INVALID_HANDLE" =>
+                        {
+                            tokens.tokens.push(Token::implicit(&sub_child, "null"))
+                        }
+                        _ => collect_node_tokens(&sub_child, source, options, tokens)?,
+                    }
+                }
+            }
+            _ => collect_node_tokens(&child, source, options, tokens)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect the tokens of a functag in the form of the typedef it is converted to:
+/// `functag public Foo(x)` is read as `typedef Foo = function int (int x)`.
+///
+/// # Arguments
+///
+/// * `node`    - The functag node.
+/// * `source`  - The source of the tree.
+/// * `options` - What else to leave out.
+/// * `tokens`  - The tokens to push into.
+fn collect_functag_tokens(
+    node: &Node,
+    source: &[u8],
+    options: &CollectOptions,
+    tokens: &mut Tokens,
+) -> Result<(), Utf8Error> {
+    tokens.tokens.push(Token::implicit(node, "typedef"));
+    let mut return_type = Token::implicit(node, "int");
+    let mut cursor = node.walk();
+    // The tag can come before or after the name.
+    for child in node.children(&mut cursor) {
+        match child.kind().borrow() {
+            "symbol" => tokens.tokens.push(Token::new(&child, source)?),
+            "old_type" => return_type = converted_tag(&child, source)?,
+            "argument_declarations" => {
+                tokens.tokens.push(Token::implicit(&child, "="));
+                tokens.tokens.push(Token::implicit(&child, "function"));
+                tokens.tokens.push(return_type.clone());
+                collect_untagged_argument_tokens(&child, source, options, tokens)?;
+            }
+            "functag" | "public" => continue,
+            _ => collect_node_tokens(&child, source, options, tokens)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect the tokens of a funcenum in the form of the typeset it is converted to:
+/// `funcenum Foo { public(x), }` is read as `typeset Foo { function int (int x); }`.
+///
+/// # Arguments
+///
+/// * `node`    - The funcenum node.
+/// * `source`  - The source of the tree.
+/// * `options` - What else to leave out.
+/// * `tokens`  - The tokens to push into.
+fn collect_funcenum_tokens(
+    node: &Node,
+    source: &[u8],
+    options: &CollectOptions,
+    tokens: &mut Tokens,
+) -> Result<(), Utf8Error> {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind().borrow() {
+            "funcenum" => tokens.tokens.push(Token::implicit(&child, "typeset")),
+            "funcenum_member" => {
+                tokens.tokens.push(Token::implicit(&child, "function"));
+                let mut return_type = Token::implicit(&child, "int");
+                let mut sub_cursor = child.walk();
+                for sub_child in child.children(&mut sub_cursor) {
+                    match sub_child.kind().borrow() {
+                        "old_type" => return_type = converted_tag(&sub_child, source)?,
+                        "argument_declarations" => {
+                            tokens.tokens.push(return_type.clone());
+                            collect_untagged_argument_tokens(&sub_child, source, options, tokens)?;
+                        }
+                        "public" => continue,
+                        _ => collect_node_tokens(&sub_child, source, options, tokens)?,
+                    }
+                }
+            }
+            // The members of a typeset are separated by semicolons.
+            "," => continue,
+            _ => collect_node_tokens(&child, source, options, tokens)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect the tokens of the old syntax argument declarations of a functag or a
+/// funcenum, where the untagged arguments are converted to `int`s.
+///
+/// # Arguments
+///
+/// * `node`    - The argument declarations node.
+/// * `source`  - The source of the tree.
+/// * `options` - What else to leave out.
+/// * `tokens`  - The tokens to push into.
+fn collect_untagged_argument_tokens(
+    node: &Node,
+    source: &[u8],
+    options: &CollectOptions,
+    tokens: &mut Tokens,
+) -> Result<(), Utf8Error> {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        let typed_kinds: &[&str] = match child.kind().borrow() {
+            "argument_declaration" => &["argument_type"],
+            "rest_argument" => &["old_type", "type"],
+            _ => {
+                collect_node_tokens(&child, source, options, tokens)?;
+                continue;
+            }
+        };
+        let mut sub_cursor = child.walk();
+        let mut is_typed = false;
+        for sub_child in child.children(&mut sub_cursor) {
+            let kind = sub_child.kind();
+            if typed_kinds.contains(&kind.borrow()) {
+                is_typed = true;
+            }
+            // The implicit type comes before the name, after `const`.
+            if !is_typed && (kind == "symbol" || kind == "...") {
+                tokens.tokens.push(Token::implicit(&sub_child, "int"));
+                is_typed = true;
+            }
+            collect_node_tokens(&sub_child, source, options, tokens)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect the tokens of the type of an argument, with the `&` after the type, where
+/// the conversion of an old syntax argument moves it (`&Float:x` to `float &x`).
+///
+/// # Arguments
+///
+/// * `node`    - The argument type node.
+/// * `source`  - The source of the tree.
+/// * `options` - What else to leave out.
+/// * `tokens`  - The tokens to push into.
+fn collect_argument_type_tokens(
+    node: &Node,
+    source: &[u8],
+    options: &CollectOptions,
+    tokens: &mut Tokens,
+) -> Result<(), Utf8Error> {
+    let mut reference = None;
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if child.kind() == "&" {
+            reference = Some(Token::new(&child, source)?);
+            continue;
+        }
+        collect_node_tokens(&child, source, options, tokens)?;
+    }
+    tokens.tokens.extend(reference);
+
+    Ok(())
+}
+
+/// Returns the token of the type an old tag is converted to. The tags which
+/// cannot be converted are read as they are.
+///
+/// # Arguments
+///
+/// * `node`   - The old type node.
+/// * `source` - The source of the tree.
+fn converted_tag(node: &Node, source: &[u8]) -> Result<Token, Utf8Error> {
+    let mut token = Token::new(node, source)?;
+    if let Some(type_) = convert_old_tag(&token.text) {
+        token.text = type_;
+    }
+
+    Ok(token)
+}
+
+/// Compare two sequences of tokens and describe the first difference.
+///
+/// # Arguments
+///
+/// * `expected` - The tokens of the input.
+/// * `found`    - The tokens of the output.
+/// * `name`     - Name of the tokens, used in the error.
+fn compare_tokens(expected: &[Token], found: &[Token], name: &str) -> Result<(), FormatError> {
+    for index in 0..expected.len().max(found.len()) {
+        let expected_token = expected.get(index);
+        let found_token = found.get(index);
+        if expected_token.map(|token| &token.text) == found_token.map(|token| &token.text) {
+            continue;
+        }
+        let expected_message = match expected_token {
+            Some(token) => format!(
+                "expected {} `{}` at {}:{} of the input",
+                name,
+                token.text,
+                token.row + 1,
+                token.column + 1
+            ),
+            None => "expected the end of the input".to_string(),
+        };
+        let found_message = match found_token {
+            Some(token) => format!(
+                "found {} `{}` at {}:{} of the output",
+                name,
+                token.text,
+                token.row + 1,
+                token.column + 1
+            ),
+            None => "found the end of the output".to_string(),
+        };
+        return Err(FormatError::Verification(format!(
+            "{}, {}",
+            expected_message, found_message
+        )));
+    }
+
+    Ok(())
+}
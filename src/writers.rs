@@ -1,10 +1,15 @@
-use crate::settings::Settings;
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticKind},
+    doc::{self, Doc, PrintOptions},
+    settings::Settings,
+};
 
 use self::{expressions::write_expression, preproc::insert_break};
 
 use std::{
     borrow::{Borrow, Cow},
     collections::HashSet,
+    ops::Range,
     str::Utf8Error,
 };
 use tree_sitter::{Language, Node, Point};
@@ -19,6 +24,7 @@ pub mod functions;
 pub mod hardcoded_symbols;
 pub mod methodmaps;
 pub mod old_variables;
+pub mod precedence;
 pub mod preproc;
 pub mod source_file;
 pub mod statements;
@@ -32,11 +38,14 @@ pub struct Writer<'a> {
     pub language: &'a Language,
     pub indent: usize,
     pub indent_string: String,
-    pub skip: u8,
     pub settings: &'a Settings,
     pub _statement_kinds: HashSet<String>,
     pub _expression_kinds: HashSet<String>,
     pub _literal_kinds: HashSet<String>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Kinds and byte ranges of the nodes copied verbatim instead of being formatted,
+    /// see [`Writer::record_verbatim`].
+    pub verbatim: Vec<(String, Range<usize>)>,
 }
 
 impl Writer<'_> {
@@ -49,6 +58,118 @@ impl Writer<'_> {
         self.output.push('\n');
     }
 
+    /// Record a node copied verbatim, for the coverage report. Only the nodes with
+    /// children are recorded, as copying a single token is how it is formatted.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The node which was copied.
+    fn record_verbatim(&mut self, node: &Node) {
+        if node.child_count() > 0 {
+            self.verbatim
+                .push((node.kind().to_string(), byte_range(node)));
+        }
+    }
+
+    /// Insert empty lines before a declaration, up to a number of empty lines.
+    /// The empty lines already written after the previous node, kept from the
+    /// source by [`preproc::insert_break`], count towards that number, so that
+    /// formatting the output again does not add more of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `nb_lines` - The number of empty lines to insert before the declaration.
+    fn write_empty_lines(&mut self, nb_lines: usize) {
+        let line_breaks = self.output.chars().rev().take_while(|&c| c == '\n').count();
+        // The first line break ends the line of the previous node.
+        let empty_lines = line_breaks.saturating_sub(1);
+        self.output
+            .push_str("\n".repeat(nb_lines.saturating_sub(empty_lines)).as_str());
+    }
+
+    /// Returns the column at which the next character of the output will be written.
+    /// Tabs are expanded to the next multiple of the indent width.
+    fn column(&self) -> usize {
+        let line = match self.output.rfind('\n') {
+            Some(index) => &self.output[index + 1..],
+            None => self.output.as_str(),
+        };
+        let tab_width = usize::try_from(self.settings.indent_width.max(1)).unwrap();
+        let mut column = 0;
+        for c in line.chars() {
+            if c == '\t' {
+                column += tab_width - column % tab_width;
+            } else {
+                column += 1;
+            }
+        }
+
+        column
+    }
+
+    /// Returns true if a length is greater than the maximum line width.
+    ///
+    /// # Arguments
+    ///
+    /// * `length` - The length to compare to the maximum line width.
+    fn exceeds_line_width(&self, length: usize) -> bool {
+        self.settings.max_line_width > 0
+            && length > usize::try_from(self.settings.max_line_width).unwrap()
+    }
+
+    /// Lay out a document at the current column and write it.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc` - The document to write.
+    fn write_doc(&mut self, doc: &Doc) {
+        let max_line_width = match self.settings.max_line_width {
+            0 => usize::MAX / 2,
+            width => usize::try_from(width).unwrap(),
+        };
+        let printed = doc::print(
+            doc,
+            &PrintOptions {
+                max_line_width,
+                indent_string: self.indent_string.as_str(),
+                tab_width: usize::try_from(self.settings.indent_width).unwrap(),
+                indent: self.indent,
+                column: self.column(),
+            },
+        );
+        self.output.push_str(printed.as_str());
+    }
+
+    /// Run a writer function and return what it wrote instead of writing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `write` - The writer function to capture the output of.
+    fn capture<F>(&mut self, write: F) -> Result<String, Utf8Error>
+    where
+        F: FnOnce(&mut Self) -> Result<(), Utf8Error>,
+    {
+        let output = std::mem::take(&mut self.output);
+        let result = write(self);
+        let captured = std::mem::replace(&mut self.output, output);
+        result?;
+
+        Ok(captured)
+    }
+
+    /// Indent a new line, then pad it with spaces up to a column.
+    ///
+    /// # Arguments
+    ///
+    /// * `column` - The column to align the next character of the output to.
+    fn align_to(&mut self, column: usize) {
+        self.write_indent();
+        let current = self.column();
+        if column > current {
+            self.output.push_str(" ".repeat(column - current).as_str());
+        }
+    }
+
     fn is_statement(&mut self, kind: &Cow<str>) -> bool {
         return self._statement_kinds.contains(&kind.to_string());
     }
@@ -60,6 +181,34 @@ impl Writer<'_> {
     fn is_literal(&mut self, kind: &Cow<str>) -> bool {
         return self._literal_kinds.contains(&kind.to_string());
     }
+
+    /// Report a node which the writer function does not know how to format.
+    ///
+    /// # Arguments
+    ///
+    /// * `node`      - The unexpected node.
+    /// * `writer_fn` - Name of the writer function which met the node.
+    fn unexpected(&mut self, node: &Node, writer_fn: &'static str) {
+        self.diagnostics.push(Diagnostic::new(
+            DiagnosticKind::UnexpectedNode,
+            node,
+            writer_fn,
+        ));
+    }
+
+    /// Report a node which could not be converted to the new syntax.
+    ///
+    /// # Arguments
+    ///
+    /// * `node`      - The node which was kept in the old syntax.
+    /// * `writer_fn` - Name of the writer function which met the node.
+    fn unconverted(&mut self, node: &Node, writer_fn: &'static str) {
+        self.diagnostics.push(Diagnostic::new(
+            DiagnosticKind::UnconvertedOldSyntax,
+            node,
+            writer_fn,
+        ));
+    }
 }
 
 pub fn write_comment(node: &Node, writer: &mut Writer) -> Result<(), Utf8Error> {
@@ -88,6 +237,8 @@ fn write_dynamic_array(node: Node, writer: &mut Writer) -> Result<(), Utf8Error>
     let mut cursor = node.walk();
     for child in node.children(&mut cursor) {
         match child.kind().borrow() {
+            // The keyword was already written.
+            "new" => continue,
             "type" => write_node(&child, writer)?,
             // TODO: Handle different cases here.
             _ => write_node(&child, writer)?,
@@ -135,6 +286,7 @@ fn write_fixed_dimension(
 }
 
 fn write_node(node: &Node, writer: &mut Writer) -> Result<(), Utf8Error> {
+    writer.record_verbatim(node);
     writer
         .output
         .push_str(node.utf8_text(writer.source)?.borrow());
@@ -175,6 +327,229 @@ fn prev_sibling_end(node: &Node) -> Option<Point> {
     return Some(prev_node.unwrap().end_position());
 }
 
+/// Returns true if a node contains syntax errors which cannot be skipped by
+/// copying a statement of a nested block verbatim.
+///
+/// # Arguments
+///
+/// * `node` - The node to check.
+fn has_error_outside_blocks(node: &Node) -> bool {
+    if node.is_error() || node.is_missing() {
+        return true;
+    }
+    if !node.has_error() {
+        return false;
+    }
+    let mut cursor = node.walk();
+    if node.kind() == "block" {
+        // Statements of a block are copied verbatim if they contain errors,
+        // only the braces of the block itself have to be valid.
+        return node
+            .children(&mut cursor)
+            .any(|child| (child.kind() == "{" || child.kind() == "}") && child.is_missing());
+    }
+    let has_error = node
+        .children(&mut cursor)
+        .any(|child| has_error_outside_blocks(&child));
+
+    has_error
+}
+
+/// Copy a node containing syntax errors as is, with the indentation it
+/// has in the source, and report it.
+///
+/// # Arguments
+///
+/// * `node`      - The node to copy.
+/// * `writer`    - The writer object.
+/// * `writer_fn` - Name of the writer function which skipped the node.
+fn write_verbatim(
+    node: &Node,
+    writer: &mut Writer,
+    writer_fn: &'static str,
+) -> Result<(), Utf8Error> {
+    write_original(node, node, writer)?;
+    writer.diagnostics.push(Diagnostic::new(
+        DiagnosticKind::SkippedSyntaxError,
+        node,
+        writer_fn,
+    ));
+
+    Ok(())
+}
+
+/// Copy the source from the start of a node to the end of another as is, with
+/// the indentation the first node has in the source.
+///
+/// # Arguments
+///
+/// * `first`  - The first node to copy.
+/// * `last`   - The last node to copy.
+/// * `writer` - The writer object.
+fn write_original(first: &Node, last: &Node, writer: &mut Writer) -> Result<(), Utf8Error> {
+    let start = usize::try_from(first.start_byte()).unwrap();
+    let end = usize::try_from(last.end_byte()).unwrap();
+    let line_start = writer.source[..start]
+        .iter()
+        .rposition(|&c| c == b'\n')
+        .map_or(0, |index| index + 1);
+    let leading = std::str::from_utf8(&writer.source[line_start..start])?;
+    if writer.output.is_empty() || writer.output.ends_with('\n') {
+        if leading.trim().is_empty() {
+            writer.output.push_str(leading);
+        } else {
+            writer.write_indent();
+        }
+    }
+    writer
+        .output
+        .push_str(std::str::from_utf8(&writer.source[start..end])?);
+
+    // None of the copied nodes is formatted, single tokens included.
+    let mut node = Some(*first);
+    while let Some(copied) = node {
+        if copied.is_named() {
+            writer
+                .verbatim
+                .push((copied.kind().to_string(), byte_range(&copied)));
+        }
+        if copied.end_byte() >= last.end_byte() {
+            break;
+        }
+        node = copied.next_sibling();
+    }
+
+    Ok(())
+}
+
+/// Comments controlling the formatter.
+enum Directive {
+    /// `// sp-format off`, disables the formatting until the next `on` directive.
+    Off,
+    /// `// sp-format on`, enables the formatting back.
+    On,
+    /// `// sp-format ignore-next`, disables the formatting of the next node.
+    IgnoreNext,
+}
+
+/// Returns the directive a node holds, if it is a directive comment.
+///
+/// # Arguments
+///
+/// * `node`   - The node to check.
+/// * `source` - The source of the node.
+fn directive(node: &Node, source: &[u8]) -> Option<Directive> {
+    if node.kind() != "comment" {
+        return None;
+    }
+    let text = node.utf8_text(source).ok()?;
+    match text.trim().strip_prefix("//")?.trim() {
+        "sp-format off" => Some(Directive::Off),
+        "sp-format on" => Some(Directive::On),
+        "sp-format ignore-next" => Some(Directive::IgnoreNext),
+        _ => None,
+    }
+}
+
+/// A child of a container node, as seen through the directive comments.
+pub enum Child<'tree> {
+    /// A child to format.
+    Formatted(Node<'tree>),
+    /// An `sp-format off` or `sp-format ignore-next` directive and the last of the
+    /// siblings it disables, which are copied as is.
+    Disabled(Node<'tree>, Node<'tree>),
+}
+
+/// Returns the children of a node, with the `sp-format off` and `sp-format ignore-next`
+/// directives grouped with the siblings they disable.
+///
+/// # Arguments
+///
+/// * `node`   - The container node.
+/// * `source` - The source of the node.
+pub fn children_with_directives<'tree>(node: &Node<'tree>, source: &[u8]) -> Vec<Child<'tree>> {
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.children(&mut cursor).collect();
+    let mut result = vec![];
+    let mut index = 0;
+    while index < children.len() {
+        let child = children[index];
+        let mut last = index;
+        match directive(&child, source) {
+            Some(Directive::Off) => {
+                // Disable up to the next `on` directive, or to the end of the container.
+                while last + 1 < children.len() && children[last + 1].kind() != "}" {
+                    last += 1;
+                    if let Some(Directive::On) = directive(&children[last], source) {
+                        break;
+                    }
+                }
+            }
+            Some(Directive::IgnoreNext) => {
+                // Disable up to the next node which is not a comment, and its separator.
+                while last + 1 < children.len() && children[last + 1].kind() != "}" {
+                    last += 1;
+                    if children[last].kind() != "comment" {
+                        break;
+                    }
+                }
+                if last + 1 < children.len()
+                    && matches!(children[last + 1].kind().borrow(), "," | ";")
+                {
+                    last += 1;
+                }
+            }
+            _ => {
+                result.push(Child::Formatted(child));
+                index += 1;
+                continue;
+            }
+        }
+        result.push(Child::Disabled(child, children[last]));
+        index = last + 1;
+    }
+
+    result
+}
+
+/// Write the children of a container node with a writer function, except for the
+/// ones disabled by a directive, which are copied as is.
+///
+/// # Arguments
+///
+/// * `node`        - The container node.
+/// * `writer`      - The writer object.
+/// * `write_child` - The function which writes a child that is not disabled.
+fn write_children<'tree, F>(
+    node: &Node<'tree>,
+    writer: &mut Writer,
+    mut write_child: F,
+) -> Result<(), Utf8Error>
+where
+    F: FnMut(Node<'tree>, &mut Writer) -> Result<(), Utf8Error>,
+{
+    for child in children_with_directives(node, writer.source) {
+        match child {
+            Child::Formatted(child) => write_child(child, writer)?,
+            Child::Disabled(first, last) => {
+                write_original(&first, &last, writer)?;
+                insert_break(&last, writer);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the range of bytes of a node in the source.
+///
+/// # Arguments
+///
+/// * `node` - The node to get the range of.
+fn byte_range(node: &Node) -> Range<usize> {
+    usize::try_from(node.start_byte()).unwrap()..usize::try_from(node.end_byte()).unwrap()
+}
+
 /// Returns the length of a node.
 ///
 /// # Arguments
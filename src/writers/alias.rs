@@ -21,7 +21,7 @@ pub fn write_alias_declaration(node: Node, writer: &mut Writer) -> Result<(), Ut
         && prev_kind != "alias_declaration"
     {
         // Insert new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
     let mut cursor = node.walk();
@@ -48,7 +48,7 @@ pub fn write_alias_declaration(node: Node, writer: &mut Writer) -> Result<(), Ut
                 if writer.is_statement(&kind) {
                     write_statement(child, writer, false, false)?;
                 } else {
-                    println!("Unexpected kind {} in write_alias_declaration.", kind);
+                    writer.unexpected(&child, "write_alias_declaration");
                 }
             }
         }
@@ -68,7 +68,7 @@ pub fn write_alias_assignment(node: Node, writer: &mut Writer) -> Result<(), Utf
         && prev_kind != "alias_assignment"
     {
         // Insert two new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
     let mut cursor = node.walk();
@@ -89,7 +89,7 @@ pub fn write_alias_assignment(node: Node, writer: &mut Writer) -> Result<(), Utf
                 if writer.is_statement(&kind) {
                     write_statement(child, writer, false, false)?;
                 } else {
-                    println!("Unexpected kind {} in write_alias_declaration.", kind);
+                    writer.unexpected(&child, "write_alias_assignment");
                 }
             }
         }
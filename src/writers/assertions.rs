@@ -16,7 +16,7 @@ pub fn write_assertion(node: &Node, writer: &mut Writer) -> Result<(), Utf8Error
             "function_call_arguments" => write_function_call_arguments(child, writer)?,
             "comment" => write_comment(&child, writer)?,
             ";" => continue,
-            _ => println!("Unexpected kind {} in write_assertion.", kind),
+            _ => writer.unexpected(&child, "write_assertion"),
         }
     }
     writer.output.push(';');
@@ -4,7 +4,8 @@ use tree_sitter::Node;
 
 use super::{
     functions::write_argument_declarations, prev_sibling_kind, statements::write_block,
-    variables::write_type, write_comment, write_fixed_dimension, write_node, Writer,
+    variables::write_type, write_children, write_comment, write_fixed_dimension, write_node,
+    Writer,
 };
 
 pub fn write_enum_struct(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
@@ -13,12 +14,10 @@ pub fn write_enum_struct(node: Node, writer: &mut Writer) -> Result<(), Utf8Erro
 
     if !prev_kind.starts_with("preproc_") && prev_kind != "" && prev_kind != "comment" {
         // Insert new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
-    let mut cursor = node.walk();
-
-    for child in node.children(&mut cursor) {
+    write_children(&node, writer, |child, writer| {
         let kind = child.kind();
         match kind.borrow() {
             "enum" | "struct" => {
@@ -43,10 +42,12 @@ pub fn write_enum_struct(node: Node, writer: &mut Writer) -> Result<(), Utf8Erro
             "enum_struct_field" => write_enum_struct_field(child, writer)?,
             "enum_struct_method" => write_enum_struct_method(child, writer)?,
             _ => {
-                println!("Unexpected kind {} in write_enum_struct.", kind);
+                writer.unexpected(&child, "write_enum_struct");
             }
         }
-    }
+
+        Ok(())
+    })?;
     writer.breakl();
 
     Ok(())
@@ -62,7 +63,7 @@ fn write_enum_struct_field(node: Node, writer: &mut Writer) -> Result<(), Utf8Er
         && prev_kind != "enum_struct_field"
     {
         // Insert new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
     writer.write_indent();
@@ -77,7 +78,7 @@ fn write_enum_struct_field(node: Node, writer: &mut Writer) -> Result<(), Utf8Er
             "fixed_dimension" => write_fixed_dimension(child, writer, true)?,
             ";" => write_node(&child, writer)?,
             _ => {
-                println!("Unexpected kind {} in write_enum_struct_field.", kind);
+                writer.unexpected(&child, "write_enum_struct_field");
             }
         }
     }
@@ -92,7 +93,7 @@ fn write_enum_struct_method(node: Node, writer: &mut Writer) -> Result<(), Utf8E
 
     if !prev_kind.starts_with("preproc_") && prev_kind != "{" && prev_kind != "comment" {
         // Insert new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
     writer.write_indent();
@@ -115,7 +116,7 @@ fn write_enum_struct_method(node: Node, writer: &mut Writer) -> Result<(), Utf8E
                 }
             }
             _ => {
-                println!("Unexpected kind {} in write_enum_struct_method.", kind);
+                writer.unexpected(&child, "write_enum_struct_method");
             }
         }
     }
@@ -3,7 +3,8 @@ use std::{borrow::Borrow, str::Utf8Error};
 use tree_sitter::Node;
 
 use super::{
-    expressions::write_expression, prev_sibling_kind, write_fixed_dimension, write_node, Writer,
+    expressions::write_expression, prev_sibling_kind, write_children, write_fixed_dimension,
+    write_node, Writer,
 };
 
 pub fn write_enum(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
@@ -12,7 +13,7 @@ pub fn write_enum(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
 
     if !prev_kind.starts_with("preproc_") && prev_kind != "" && prev_kind != "comment" {
         // Insert new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
     let mut cursor = node.walk();
@@ -32,7 +33,7 @@ pub fn write_enum(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
                     write_node(&child, writer)?;
                     writer.output.push(' ');
                 } else {
-                    println!("Unexpected kind {} in write_enum.", kind);
+                    writer.unexpected(&child, "write_enum");
                 }
             }
         }
@@ -43,9 +44,7 @@ pub fn write_enum(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
 }
 
 fn write_enum_entries(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    let mut cursor = node.walk();
-
-    for child in node.children(&mut cursor) {
+    write_children(&node, writer, |child, writer| {
         let kind = child.kind();
         match kind.borrow() {
             "{" => {
@@ -62,7 +61,7 @@ fn write_enum_entries(node: Node, writer: &mut Writer) -> Result<(), Utf8Error>
                 writer.indent -= 1;
             }
             "enum_entry" => write_enum_entry(child, writer)?,
-            "," => continue,
+            "," => {}
             _ => {
                 if writer.is_expression(&kind) {
                     write_expression(child, writer)?;
@@ -72,13 +71,13 @@ fn write_enum_entries(node: Node, writer: &mut Writer) -> Result<(), Utf8Error>
                     write_node(&child, writer)?;
                     writer.output.push(' ');
                 } else {
-                    println!("Unexpected kind {} in write_enum_entries.", kind);
+                    writer.unexpected(&child, "write_enum_entries");
                 }
             }
         }
-    }
 
-    Ok(())
+        Ok(())
+    })
 }
 
 fn write_enum_entry(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
@@ -97,7 +96,7 @@ fn write_enum_entry(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
                 if writer.is_expression(&kind) {
                     write_expression(child, writer)?;
                 } else {
-                    println!("Unexpected kind {} in write_enum_entry.", kind);
+                    writer.unexpected(&child, "write_enum_entry");
                 }
             }
         }
@@ -1,50 +1,255 @@
-use super::{write_dimension, write_dynamic_array, write_node, Writer};
+use super::{
+    precedence::{
+        self, binary_operand_precedences, needs_clarifying_parentheses, node_operator, ASSIGNMENT,
+        PRIMARY, TERNARY,
+    },
+    write_dimension, write_dynamic_array, Writer,
+};
+use crate::doc::Doc;
 use std::{borrow::Borrow, str::Utf8Error};
 
 use tree_sitter::Node;
 
+/// Write an expression. The expression is laid out to fit in the maximum
+/// line width, starting at the current column of the writer.
+///
+/// # Arguments
+///
+/// * `node`   - The expression node to write.
+/// * `writer` - The writer object.
 pub fn write_expression(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    match node.kind().borrow() {
-        "symbol" | "null" | "this" | "int_literal " | "bool_literal" | "char_literal"
-        | "float_literal" | "string_literal" => write_node(&node, writer)?,
-        "binary_expression" => write_binary_expression(node, writer)?,
-        "unary_expression" => write_unary_expression(node, writer)?,
-        "update_expression" => write_update_expression(node, writer)?,
-        "parenthesized_expression" => write_parenthesized_expression(node, writer)?,
-        "comma_expression" => write_comma_expression(node, writer)?,
-        "scope_access" => write_scope_access(node, writer)?,
-        "view_as" => write_view_as(node, writer)?,
-        "old_type_cast" => write_old_type_cast(node, writer)?,
-        "ternary_expression" => write_ternary_expression(node, writer)?,
-        "concatenated_string" => write_concatenated_string(node, writer)?,
-        "array_indexed_access" => write_array_indexed_access(node, writer)?,
-        "field_access" => write_field_access(node, writer)?,
-        "new_instance" => write_new_instance(node, writer)?,
-        "function_call" => write_function_call(node, writer)?,
-        "assignment_expression" => write_assignment_expression(node, writer)?,
-        "array_literal" => write_array_literal(node, writer)?,
-        "sizeof_expression" => write_sizeof_expression(node, writer)?,
-        _ => write_node(&node, writer)?,
-    };
+    let doc = build_operand(node, ASSIGNMENT, writer)?;
+    writer.write_doc(&doc);
 
     Ok(())
 }
 
-fn write_binary_expression(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    write_expression(node.child_by_field_name("left").unwrap(), writer)?;
-    writer.output.push(' ');
-    write_node(&node.child_by_field_name("operator").unwrap(), writer)?;
-    writer.output.push(' ');
-    write_expression(node.child_by_field_name("right").unwrap(), writer)?;
+/// Build the document of an expression.
+///
+/// # Arguments
+///
+/// * `node`   - The expression node to build the document of.
+/// * `writer` - The writer object.
+pub fn build_expression(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let doc = match node.kind().borrow() {
+        "symbol" | "null" | "this" | "int_literal" | "bool_literal" | "char_literal"
+        | "float_literal" | "string_literal" => build_node(&node, writer)?,
+        "binary_expression" => build_binary_expression(node, writer)?,
+        "unary_expression" => build_unary_expression(node, writer)?,
+        "update_expression" => build_update_expression(node, writer)?,
+        "parenthesized_expression" => build_parenthesized_expression(node, writer)?,
+        "comma_expression" => build_comma_expression(node, writer)?,
+        "scope_access" => build_scope_access(node, writer)?,
+        "view_as" => build_view_as(node, writer)?,
+        "old_type_cast" => build_old_type_cast(node, writer)?,
+        "ternary_expression" => build_ternary_expression(node, writer)?,
+        "concatenated_string" => build_concatenated_string(node, writer)?,
+        "array_indexed_access" => build_array_indexed_access(node, writer)?,
+        "field_access" => build_field_access(node, writer)?,
+        "new_instance" => build_new_instance(node, writer)?,
+        "function_call" => build_function_call(node, writer)?,
+        "assignment_expression" => build_assignment_expression(node, writer)?,
+        "array_literal" => build_array_literal(node, writer)?,
+        "sizeof_expression" => build_sizeof_expression(node, writer)?,
+        "comment" => build_comment(&node, writer)?,
+        _ => build_node(&node, writer)?,
+    };
 
-    Ok(())
+    Ok(doc)
+}
+
+/// Build the document of an operand. If the operand is parenthesized and the
+/// parentheses are redundant in a position requiring a minimum precedence, they
+/// are removed when `remove_redundant_parentheses` is set. Nested parentheses
+/// are always collapsed in that case. The parentheses around an assignment used
+/// as a condition are kept, as the compiler warns about the assignment otherwise.
+///
+/// # Arguments
+///
+/// * `node`       - The operand node to build the document of.
+/// * `precedence` - The minimum precedence the operand must have to be written without parentheses.
+/// * `writer`     - The writer object.
+pub fn build_operand(node: Node, precedence: u8, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    if !writer.settings.remove_redundant_parentheses || node.kind() != "parenthesized_expression" {
+        return build_expression(node, writer);
+    }
+    let mut expression = node;
+    while expression.kind() == "parenthesized_expression" {
+        expression = expression.child_by_field_name("expression").unwrap();
+    }
+    let is_assigned_condition = expression.kind() == "assignment_expression"
+        && node.parent().map_or(false, |parent| {
+            matches!(
+                parent.kind().borrow(),
+                "condition_statement" | "while_loop" | "do_while_loop" | "for_loop"
+            )
+        });
+    if precedence::precedence(&expression) >= precedence && !is_assigned_condition {
+        return build_expression(expression, writer);
+    }
+
+    Ok(Doc::concat(vec![
+        Doc::text("("),
+        build_expression(expression, writer)?,
+        Doc::text(")"),
+    ]))
+}
+
+/// Build the document of a node by copying its text.
+///
+/// # Arguments
+///
+/// * `node`   - The node to build the document of.
+/// * `writer` - The writer object.
+fn build_node(node: &Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    writer.record_verbatim(node);
+    Ok(Doc::text(node.utf8_text(writer.source)?))
 }
 
-fn write_old_type_cast(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    write_old_type(node.child_by_field_name("type").unwrap(), writer)?;
-    write_expression(node.child_by_field_name("value").unwrap(), writer)?;
+/// Build the document of a comment nested in an expression. Line comments
+/// force the enclosing groups to break, to avoid commenting out the rest of the line.
+///
+/// # Arguments
+///
+/// * `node`   - The comment node to build the document of.
+/// * `writer` - The writer object.
+pub fn build_comment(node: &Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let text = node.utf8_text(writer.source)?;
+    let text = text.trim();
+    if text.starts_with("//") {
+        return Ok(Doc::concat(vec![Doc::text(text), Doc::BreakParent]));
+    }
 
-    Ok(())
+    Ok(Doc::text(text))
+}
+
+fn build_binary_expression(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let operator_node = node.child_by_field_name("operator").unwrap();
+    if matches!(operator_node.kind().borrow(), "&&" | "||") {
+        return build_logical_chain(node, writer);
+    }
+    let (left_precedence, right_precedence) = binary_operand_precedences(&node);
+    let left = build_binary_operand(
+        &node,
+        node.child_by_field_name("left").unwrap(),
+        left_precedence,
+        writer,
+    )?;
+    let operator = build_node(&operator_node, writer)?;
+    let right = build_binary_operand(
+        &node,
+        node.child_by_field_name("right").unwrap(),
+        right_precedence,
+        writer,
+    )?;
+
+    Ok(Doc::group(Doc::concat(vec![
+        left,
+        Doc::text(" "),
+        operator,
+        Doc::indent(Doc::concat(vec![Doc::Line, right])),
+    ])))
+}
+
+/// Build the document of a chain of `&&` or `||` operators, like `a && b && c`.
+/// If the chain does not fit on the line, it is broken at every operator and the
+/// following lines are indented once. The operators lead the lines when
+/// `break_before_logical_operators` is set, and trail them otherwise.
+///
+/// # Arguments
+///
+/// * `node`   - The outermost binary expression node of the chain.
+/// * `writer` - The writer object.
+fn build_logical_chain(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let operator = node
+        .child_by_field_name("operator")
+        .unwrap()
+        .kind()
+        .to_string();
+    // `a && b && c` is `(a && b) && c`, walk down the left operands using the same operator.
+    let mut links = vec![node];
+    let mut first = node.child_by_field_name("left").unwrap();
+    while first.kind() == "binary_expression"
+        && first.child_by_field_name("operator").unwrap().kind() == operator
+    {
+        links.push(first);
+        first = first.child_by_field_name("left").unwrap();
+    }
+
+    let innermost = links.last().unwrap();
+    let (left_precedence, _) = binary_operand_precedences(innermost);
+    let first = build_binary_operand(innermost, first, left_precedence, writer)?;
+    let mut rest = vec![];
+    for link in links.iter().rev() {
+        let (_, right_precedence) = binary_operand_precedences(link);
+        let right = build_binary_operand(
+            link,
+            link.child_by_field_name("right").unwrap(),
+            right_precedence,
+            writer,
+        )?;
+        if writer.settings.break_before_logical_operators {
+            rest.extend([Doc::Line, Doc::text(format!("{} ", operator)), right]);
+        } else {
+            rest.extend([Doc::text(format!(" {}", operator)), Doc::Line, right]);
+        }
+    }
+
+    Ok(Doc::group(Doc::concat(vec![
+        first,
+        Doc::indent(Doc::concat(rest)),
+    ])))
+}
+
+/// Build the document of an operand of a binary expression. When
+/// `add_clarifying_parentheses` is set, operands mixing their operator with the
+/// operator of the expression in a way which is easy to misread are parenthesized.
+///
+/// # Arguments
+///
+/// * `parent`     - The binary expression node.
+/// * `node`       - The operand node to build the document of.
+/// * `precedence` - The minimum precedence the operand must have to be written without parentheses.
+/// * `writer`     - The writer object.
+fn build_binary_operand(
+    parent: &Node,
+    node: Node,
+    precedence: u8,
+    writer: &mut Writer,
+) -> Result<Doc, Utf8Error> {
+    if writer.settings.add_clarifying_parentheses {
+        // Look through the parentheses which are about to be removed.
+        let mut expression = node;
+        while writer.settings.remove_redundant_parentheses
+            && expression.kind() == "parenthesized_expression"
+        {
+            expression = expression.child_by_field_name("expression").unwrap();
+        }
+        if let (Some(parent_operator), Some(operator)) =
+            (node_operator(parent), node_operator(&expression))
+        {
+            if needs_clarifying_parentheses(&parent_operator, &operator) {
+                return Ok(Doc::concat(vec![
+                    Doc::text("("),
+                    build_expression(expression, writer)?,
+                    Doc::text(")"),
+                ]));
+            }
+        }
+    }
+
+    build_operand(node, precedence, writer)
+}
+
+fn build_old_type_cast(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let old_type = node.child_by_field_name("type").unwrap();
+    let value = build_expression(node.child_by_field_name("value").unwrap(), writer)?;
+
+    Ok(Doc::concat(vec![
+        build_node(&old_type, writer)?,
+        Doc::text(" "),
+        value,
+    ]))
 }
 
 pub fn write_old_type(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
@@ -56,203 +261,538 @@ pub fn write_old_type(node: Node, writer: &mut Writer) -> Result<(), Utf8Error>
     Ok(())
 }
 
-fn write_assignment_expression(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    write_expression(node.child_by_field_name("left").unwrap(), writer)?;
-    writer.output.push(' ');
-    write_node(&node.child_by_field_name("operator").unwrap(), writer)?;
-    writer.output.push(' ');
+fn build_assignment_expression(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let left = build_operand(node.child_by_field_name("left").unwrap(), PRIMARY, writer)?;
+    let operator = build_node(&node.child_by_field_name("operator").unwrap(), writer)?;
     let right_node = node.child_by_field_name("right").unwrap();
-    match right_node.kind().borrow() {
-        "dynamic_array" => write_dynamic_array(right_node, writer)?,
-        _ => write_expression(right_node, writer)?,
-    }
+    let right = match right_node.kind().borrow() {
+        "dynamic_array" => {
+            Doc::text(writer.capture(|writer| write_dynamic_array(right_node, writer))?)
+        }
+        _ => build_operand(right_node, ASSIGNMENT, writer)?,
+    };
 
-    Ok(())
+    Ok(Doc::concat(vec![
+        left,
+        Doc::text(" "),
+        operator,
+        Doc::text(" "),
+        right,
+    ]))
 }
 
-fn write_array_indexed_access(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
+fn build_array_indexed_access(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
     let array_node = node.child_by_field_name("array").unwrap();
-    match array_node.kind().borrow() {
-        "array_indexed_access" => write_array_indexed_access(array_node, writer)?,
+    let array = match array_node.kind().borrow() {
+        "array_indexed_access" => build_array_indexed_access(array_node, writer)?,
         // TODO: Handle "field_access" here.
-        _ => write_node(&array_node, writer)?,
-    }
-    writer.output.push('[');
-    write_expression(node.child_by_field_name("index").unwrap(), writer)?;
-    writer.output.push(']');
-
-    Ok(())
+        _ => build_node(&array_node, writer)?,
+    };
+    let index = build_operand(
+        node.child_by_field_name("index").unwrap(),
+        ASSIGNMENT,
+        writer,
+    )?;
+
+    Ok(Doc::concat(vec![
+        array,
+        Doc::text("["),
+        index,
+        Doc::text("]"),
+    ]))
 }
 
-fn write_field_access(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    write_expression(node.child_by_field_name("target").unwrap(), writer)?;
-    writer.output.push('.');
-    write_node(&node.child_by_field_name("field").unwrap(), writer)?;
+fn build_field_access(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let target = build_operand(node.child_by_field_name("target").unwrap(), PRIMARY, writer)?;
+    let field = build_node(&node.child_by_field_name("field").unwrap(), writer)?;
 
-    Ok(())
+    Ok(Doc::concat(vec![target, Doc::text("."), field]))
 }
 
-fn write_new_instance(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    writer.output.push_str("new ");
-    write_node(&node.child_by_field_name("class").unwrap(), writer)?;
-    write_function_call_arguments(node.child_by_field_name("arguments").unwrap(), writer)?;
+fn build_new_instance(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let class = build_node(&node.child_by_field_name("class").unwrap(), writer)?;
+    let arguments =
+        build_function_call_arguments(node.child_by_field_name("arguments").unwrap(), writer)?;
 
-    Ok(())
+    Ok(Doc::concat(vec![Doc::text("new "), class, arguments]))
 }
 
-fn write_function_call(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    let function_node = node.child_by_field_name("function").unwrap();
-    write_expression(function_node, writer)?;
-    write_function_call_arguments(node.child_by_field_name("arguments").unwrap(), writer)?;
+fn build_function_call(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let function = build_operand(
+        node.child_by_field_name("function").unwrap(),
+        PRIMARY,
+        writer,
+    )?;
+    let arguments =
+        build_function_call_arguments(node.child_by_field_name("arguments").unwrap(), writer)?;
 
-    Ok(())
+    Ok(Doc::concat(vec![function, arguments]))
 }
 
-fn write_unary_expression(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    write_node(&node.child_by_field_name("operator").unwrap(), writer)?;
-    write_expression(node.child_by_field_name("argument").unwrap(), writer)?;
+fn build_unary_expression(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let operator = build_node(&node.child_by_field_name("operator").unwrap(), writer)?;
+    // Keep the parentheses of anything but primary expressions, `-(-a)` is not `--a`.
+    let argument = build_operand(
+        node.child_by_field_name("argument").unwrap(),
+        PRIMARY,
+        writer,
+    )?;
 
-    Ok(())
+    Ok(Doc::concat(vec![operator, argument]))
 }
 
-fn write_parenthesized_expression(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    // TODO: Check for literals/symbols to remove unneeded parenthesis.
-    writer.output.push('(');
+fn build_parenthesized_expression(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
     let expression_node = node.child_by_field_name("expression").unwrap();
-    match expression_node.kind().borrow() {
-        "comma_expression" => write_comma_expression(expression_node, writer)?,
-        _ => write_expression(expression_node, writer)?,
-    }
-    writer.output.push(')');
+    let expression = match expression_node.kind().borrow() {
+        "comma_expression" => build_comma_expression(expression_node, writer)?,
+        _ => build_expression(expression_node, writer)?,
+    };
 
-    Ok(())
+    Ok(Doc::concat(vec![
+        Doc::text("("),
+        expression,
+        Doc::text(")"),
+    ]))
 }
 
-fn write_comma_expression(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    write_expression(node.child_by_field_name("left").unwrap(), writer)?;
-    writer.output.push_str(", ");
-    write_expression(node.child_by_field_name("right").unwrap(), writer)?;
+fn build_comma_expression(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let left = build_expression(node.child_by_field_name("left").unwrap(), writer)?;
+    let right = build_expression(node.child_by_field_name("right").unwrap(), writer)?;
 
-    Ok(())
+    Ok(Doc::group(Doc::concat(vec![
+        left,
+        Doc::text(","),
+        Doc::Line,
+        right,
+    ])))
 }
 
-fn write_concatenated_string(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    write_node(&node.child_by_field_name("left").unwrap(), writer)?;
-    writer.output.push_str(" ... ");
+fn build_concatenated_string(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let left = build_node(&node.child_by_field_name("left").unwrap(), writer)?;
     let right_node = node.child_by_field_name("right").unwrap();
-    match right_node.kind().borrow() {
-        "concatenated_string" => write_concatenated_string(right_node, writer)?,
-        _ => write_node(&right_node, writer)?,
-    }
+    let right = match right_node.kind().borrow() {
+        "concatenated_string" => build_concatenated_string(right_node, writer)?,
+        _ => build_node(&right_node, writer)?,
+    };
 
-    Ok(())
+    Ok(Doc::group(Doc::concat(vec![
+        left,
+        Doc::text(" ..."),
+        Doc::indent(Doc::concat(vec![Doc::Line, right])),
+    ])))
 }
 
-fn write_update_expression(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
+fn build_update_expression(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
     let argument_node = node.child_by_field_name("argument").unwrap();
     let operator_node = node.child_by_field_name("operator").unwrap();
+    let argument = build_expression(argument_node, writer)?;
+    let operator = build_node(&operator_node, writer)?;
     if operator_node.end_position() <= argument_node.start_position() {
-        write_node(&operator_node, writer)?;
-        write_expression(argument_node, writer)?;
+        return Ok(Doc::concat(vec![operator, argument]));
+    }
+
+    Ok(Doc::concat(vec![argument, operator]))
+}
+
+fn build_ternary_expression(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let condition = build_operand(
+        node.child_by_field_name("condition").unwrap(),
+        TERNARY + 1,
+        writer,
+    )?;
+    let consequence = build_operand(
+        node.child_by_field_name("consequence").unwrap(),
+        TERNARY,
+        writer,
+    )?;
+    let alternative = build_operand(
+        node.child_by_field_name("alternative").unwrap(),
+        TERNARY,
+        writer,
+    )?;
+
+    Ok(Doc::group(Doc::concat(vec![
+        condition,
+        Doc::indent(Doc::concat(vec![
+            Doc::Line,
+            Doc::text("? "),
+            consequence,
+            Doc::Line,
+            Doc::text(": "),
+            alternative,
+        ])),
+    ])))
+}
+
+fn build_scope_access(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let scope = build_expression(node.child_by_field_name("scope").unwrap(), writer)?;
+    let field = build_expression(node.child_by_field_name("field").unwrap(), writer)?;
+
+    Ok(Doc::concat(vec![scope, Doc::text("::"), field]))
+}
+
+fn build_view_as(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let type_ = build_node(&node.child_by_field_name("type").unwrap(), writer)?;
+    let value = build_operand(
+        node.child_by_field_name("value").unwrap(),
+        ASSIGNMENT,
+        writer,
+    )?;
+
+    Ok(Doc::concat(vec![
+        Doc::text("view_as<"),
+        type_,
+        Doc::text(">("),
+        value,
+        Doc::text(")"),
+    ]))
+}
+
+/// Build the document of an array literal. If the elements do not fit on the
+/// line, they are broken one per line, or filled when `fill_array_literals` is set.
+/// Nested array literals are broken and indented recursively.
+///
+/// # Arguments
+///
+/// * `node`   - The array literal node to build the document of.
+/// * `writer` - The writer object.
+fn build_array_literal(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let table = if writer.settings.align_array_literal_columns {
+        numeric_table(&node, writer)?
     } else {
-        write_expression(argument_node, writer)?;
-        write_node(&operator_node, writer)?;
+        None
+    };
+    let mut cursor = node.walk();
+    // Each element, followed by its comma and its trailing comments.
+    let mut elements: Vec<Vec<Doc>> = vec![];
+    let mut leading_comments = vec![];
+    // Index of the last element in its docs, after its leading comments.
+    let mut last_element_index = None;
+    let mut rows = table.iter().flat_map(|table| table.rows.iter());
+    for child in node.children(&mut cursor) {
+        let element = match child.kind().borrow() {
+            "{" | "}" => continue,
+            "," => {
+                if let Some(element) = elements.last_mut() {
+                    element.push(Doc::text(","));
+                }
+                continue;
+            }
+            "comment" => {
+                let comment = build_comment(&child, writer)?;
+                match elements.last_mut() {
+                    Some(element) => {
+                        element.push(Doc::text(" "));
+                        element.push(comment);
+                    }
+                    None => {
+                        leading_comments.push(comment);
+                        leading_comments.push(Doc::text(" "));
+                    }
+                }
+                continue;
+            }
+            _ => {
+                let element = build_operand(child, ASSIGNMENT, writer)?;
+                match (&table, rows.next()) {
+                    (Some(table), Some(row)) => {
+                        Doc::if_break(Doc::text(table.aligned_row(row)), element)
+                    }
+                    _ => element,
+                }
+            }
+        };
+        let mut docs = std::mem::take(&mut leading_comments);
+        last_element_index = Some(docs.len());
+        docs.push(element);
+        elements.push(docs);
+    }
+    if elements.is_empty() && !leading_comments.is_empty() {
+        // Only comments, drop the space after the last one.
+        leading_comments.pop();
+        elements.push(leading_comments);
+    }
+    // The trailing comma goes right after the last element, before its trailing
+    // comments, and depends on the group of the array literal, not of the element.
+    let mut trailing = vec![];
+    if writer.settings.trailing_comma_in_array_literals {
+        if let (Some(element), Some(index)) = (elements.last_mut(), last_element_index) {
+            let mut trailing_comments = element.split_off(index + 1);
+            trailing_comments.retain(|doc| !matches!(doc, Doc::Text(text) if text == ","));
+            trailing.push(Doc::if_break(Doc::text(","), Doc::text("")));
+            trailing.extend(trailing_comments);
+        }
     }
 
-    Ok(())
+    let elements: Vec<Doc> = elements.into_iter().map(Doc::concat).collect();
+    // The rows of an aligned table always go on their own line.
+    let elements = join_items(
+        elements,
+        writer.settings.fill_array_literals && table.is_none(),
+    );
+
+    Ok(Doc::group(Doc::concat(vec![
+        Doc::text("{"),
+        Doc::indent(Doc::concat(vec![
+            Doc::Line,
+            elements,
+            Doc::concat(trailing),
+        ])),
+        Doc::Line,
+        Doc::text("}"),
+    ])))
 }
 
-fn write_ternary_expression(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    write_expression(node.child_by_field_name("condition").unwrap(), writer)?;
-    writer.output.push_str(" ? ");
-    write_expression(node.child_by_field_name("consequence").unwrap(), writer)?;
-    writer.output.push_str(" : ");
-    write_expression(node.child_by_field_name("alternative").unwrap(), writer)?;
+/// Join the items of a list with lines. If `fill` is set, each item goes on the
+/// current line if it fits, on a new one otherwise.
+///
+/// # Arguments
+///
+/// * `items` - The items of the list, including their separator.
+/// * `fill`  - Whether or not to fill the lines with as many items as fit.
+fn join_items(items: Vec<Doc>, fill: bool) -> Doc {
+    if !fill {
+        return Doc::join(items, Doc::Line);
+    }
+    let mut filled = vec![];
+    for (i, item) in items.into_iter().enumerate() {
+        if i == 0 {
+            filled.push(item);
+        } else {
+            filled.push(Doc::group(Doc::concat(vec![Doc::Line, item])));
+        }
+    }
 
-    Ok(())
+    Doc::concat(filled)
 }
 
-fn write_scope_access(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    write_expression(node.child_by_field_name("scope").unwrap(), writer)?;
-    writer.output.push_str("::");
-    write_expression(node.child_by_field_name("field").unwrap(), writer)?;
+/// A table of numbers, i.e. an array literal of array literals of numbers.
+struct NumericTable {
+    /// The numbers of each row, without whitespaces.
+    rows: Vec<Vec<String>>,
+    /// The width of each column.
+    widths: Vec<usize>,
+}
 
-    Ok(())
+impl NumericTable {
+    /// Returns a row of the table with its numbers right-aligned in their column.
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - The numbers of the row.
+    fn aligned_row(&self, row: &[String]) -> String {
+        let numbers: Vec<String> = row
+            .iter()
+            .zip(self.widths.iter())
+            .map(|(number, width)| format!("{:>width$}", number, width = width))
+            .collect();
+
+        format!("{{ {} }}", numbers.join(", "))
+    }
 }
 
-fn write_view_as(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    writer.output.push_str("view_as<");
-    write_node(&node.child_by_field_name("type").unwrap(), writer)?;
-    writer.output.push_str(">(");
-    write_expression(node.child_by_field_name("value").unwrap(), writer)?;
-    writer.output.push(')');
+/// Returns the table of numbers of an array literal, or `None` if the array literal
+/// is not a table of numbers or contains comments.
+///
+/// # Arguments
+///
+/// * `node`   - The array literal node.
+/// * `writer` - The writer object.
+fn numeric_table(node: &Node, writer: &Writer) -> Result<Option<NumericTable>, Utf8Error> {
+    let mut rows = vec![];
+    for row in array_literal_elements(node) {
+        if row.kind() != "array_literal" {
+            return Ok(None);
+        }
+        let mut numbers = vec![];
+        for element in array_literal_elements(&row) {
+            match numeric_text(&element, writer)? {
+                Some(number) => numbers.push(number),
+                None => return Ok(None),
+            }
+        }
+        rows.push(numbers);
+    }
+    if rows.is_empty() {
+        return Ok(None);
+    }
+    let mut widths: Vec<usize> = vec![];
+    for row in rows.iter() {
+        for (i, number) in row.iter().enumerate() {
+            match widths.get_mut(i) {
+                Some(width) => *width = (*width).max(number.len()),
+                None => widths.push(number.len()),
+            }
+        }
+    }
 
-    Ok(())
+    Ok(Some(NumericTable { rows, widths }))
 }
 
-fn write_array_literal(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
+/// Returns the elements and the comments of an array literal.
+///
+/// # Arguments
+///
+/// * `node` - The array literal node.
+fn array_literal_elements<'tree>(node: &Node<'tree>) -> Vec<Node<'tree>> {
     let mut cursor = node.walk();
-    writer.output.push_str("{ ");
-    for child in node.children(&mut cursor) {
-        match child.kind().borrow() {
-            "{" | "}" => continue,
-            "," => writer.output.push_str(", "),
-            _ => write_expression(child, writer)?,
+    let elements = node
+        .children(&mut cursor)
+        .filter(|child| !matches!(child.kind().borrow(), "{" | "}" | ","))
+        .collect();
+
+    elements
+}
+
+/// Returns the text of a number, without whitespaces, or `None` if the node is
+/// not a number.
+///
+/// # Arguments
+///
+/// * `node`   - The node to get the text of.
+/// * `writer` - The writer object.
+fn numeric_text(node: &Node, writer: &Writer) -> Result<Option<String>, Utf8Error> {
+    let is_number = |node: &Node| matches!(node.kind().borrow(), "int_literal" | "float_literal");
+    let is_numeric = match node.kind().borrow() {
+        "unary_expression" => {
+            node.child_by_field_name("operator")
+                .map_or(false, |operator| operator.kind() == "-")
+                && node
+                    .child_by_field_name("argument")
+                    .map_or(false, |argument| is_number(&argument))
         }
+        _ => is_number(node),
+    };
+    if !is_numeric {
+        return Ok(None);
     }
-    writer.output.push_str(" }");
 
-    Ok(())
+    Ok(Some(
+        node.utf8_text(writer.source)?.split_whitespace().collect(),
+    ))
 }
 
-fn write_sizeof_expression(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
+fn build_sizeof_expression(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
     let mut cursor = node.walk();
-    writer.output.push_str("sizeof ");
-    for child in node.children_by_field_name("type", &mut cursor) {
+    let mut docs = vec![];
+    for child in node.children(&mut cursor) {
         match child.kind().borrow() {
-            "dimension" => write_dimension(child, writer, true)?,
-            _ => write_expression(child, writer)?,
+            "sizeof" | "(" | ")" => docs.push(build_node(&child, writer)?),
+            "dimension" => docs.push(Doc::text(
+                writer.capture(|writer| write_dimension(child, writer, true))?,
+            )),
+            _ => {
+                // Separate the operand from `sizeof` if it is not parenthesized.
+                if docs.len() == 1 {
+                    docs.push(Doc::text(" "));
+                }
+                docs.push(build_expression(child, writer)?);
+            }
         }
     }
 
-    Ok(())
+    Ok(Doc::concat(docs))
 }
 
 pub fn write_function_call_arguments(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
+    let doc = build_function_call_arguments(node, writer)?;
+    writer.write_doc(&doc);
+
+    Ok(())
+}
+
+/// Build the document of the arguments of a function call. If the arguments do
+/// not fit on the line, they are broken one per line, or bin-packed when
+/// `bin_pack_arguments` is set, and the closing parenthesis goes on its own line.
+///
+/// # Arguments
+///
+/// * `node`   - The function call arguments node to build the document of.
+/// * `writer` - The writer object.
+fn build_function_call_arguments(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
     let mut cursor = node.walk();
+    // Each argument, followed by its comma and its trailing comments.
+    let mut arguments: Vec<Vec<Doc>> = vec![];
+    let mut leading_comments = vec![];
     for child in node.children(&mut cursor) {
-        match child.kind().borrow() {
-            "(" => writer.output.push('('),
-            ")" => {
-                if writer.output.ends_with(", ") {
-                    writer.output.pop();
-                    writer.output.pop();
+        let argument = match child.kind().borrow() {
+            "(" | ")" => continue,
+            "," => {
+                if let Some(argument) = arguments.last_mut() {
+                    argument.push(Doc::text(","));
+                }
+                continue;
+            }
+            "comment" => {
+                let comment = build_comment(&child, writer)?;
+                match arguments.last_mut() {
+                    Some(argument) => {
+                        argument.push(Doc::text(" "));
+                        argument.push(comment);
+                    }
+                    None => {
+                        leading_comments.push(comment);
+                        leading_comments.push(Doc::text(" "));
+                    }
                 }
-                writer.output.push(')')
+                continue;
             }
-            "," => writer.output.push_str(", "),
-            "symbol" | "ignore_argument" => write_node(&child, writer)?,
-            "named_arg" => write_named_arg(child, writer)?,
+            "symbol" | "ignore_argument" => build_node(&child, writer)?,
+            "named_arg" => build_named_arg(child, writer)?,
             _ => {
                 let kind = child.kind();
                 if writer.is_expression(&kind) {
-                    write_expression(child, writer)?
+                    build_operand(child, ASSIGNMENT, writer)?
                 } else {
-                    write_node(&child, writer)?;
+                    build_node(&child, writer)?
                 }
             }
+        };
+        let mut docs = std::mem::take(&mut leading_comments);
+        docs.push(argument);
+        arguments.push(docs);
+    }
+    // Remove the trailing comma, if any.
+    if let Some(argument) = arguments.last_mut() {
+        if let Some(Doc::Text(text)) = argument.last() {
+            if text == "," {
+                argument.pop();
+            }
+        }
+    }
+    if arguments.is_empty() {
+        if leading_comments.is_empty() {
+            return Ok(Doc::text("()"));
         }
+        // Only comments, drop the space after the last one.
+        leading_comments.pop();
+        arguments.push(leading_comments);
     }
 
-    Ok(())
+    let arguments: Vec<Doc> = arguments.into_iter().map(Doc::concat).collect();
+    let arguments = join_items(arguments, writer.settings.bin_pack_arguments);
+
+    Ok(Doc::group(Doc::concat(vec![
+        Doc::text("("),
+        Doc::indent(Doc::concat(vec![Doc::SoftLine, arguments])),
+        Doc::SoftLine,
+        Doc::text(")"),
+    ])))
 }
 
-fn write_named_arg(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    writer.output.push('.');
-    write_node(&node.child_by_field_name("name").unwrap(), writer)?;
-    writer.output.push_str(" = ");
-    // FIXME: Always write_node.
-    write_node(&node.child_by_field_name("value").unwrap(), writer)?;
+fn build_named_arg(node: Node, writer: &mut Writer) -> Result<Doc, Utf8Error> {
+    let name = build_node(&node.child_by_field_name("name").unwrap(), writer)?;
+    let value_node = node.child_by_field_name("value").unwrap();
+    let value = if writer.is_expression(&value_node.kind()) {
+        build_operand(value_node, ASSIGNMENT, writer)?
+    } else {
+        build_node(&value_node, writer)?
+    };
 
-    Ok(())
+    Ok(Doc::concat(vec![
+        Doc::text("."),
+        name,
+        Doc::text(" = "),
+        value,
+    ]))
 }
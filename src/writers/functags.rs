@@ -3,8 +3,13 @@ use std::{borrow::Borrow, str::Utf8Error};
 use tree_sitter::Node;
 
 use super::{
-    expressions::write_old_type, functions::write_argument_declarations, next_sibling_kind,
-    prev_sibling_kind, write_comment, write_node, Writer,
+    expressions::write_old_type,
+    functions::{write_argument_declarations, write_converted_argument_declarations},
+    next_sibling_kind,
+    old_variables::convert_old_tag,
+    prev_sibling_kind,
+    typedefs::write_typeset_brace,
+    write_children, write_comment, write_node, Writer,
 };
 
 pub fn write_functag(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
@@ -13,7 +18,14 @@ pub fn write_functag(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
 
     if !prev_kind.starts_with("preproc_") && prev_kind != "" && prev_kind != "comment" {
         // Insert new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
+    }
+
+    if writer.settings.convert_old_syntax {
+        if has_convertible_tags(&node, writer)? {
+            return write_converted_functag(node, writer);
+        }
+        writer.unconverted(&node, "write_functag");
     }
 
     let mut cursor = node.walk();
@@ -31,7 +43,7 @@ pub fn write_functag(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
             "argument_declarations" => write_argument_declarations(child, writer)?,
             ";" => continue,
             _ => {
-                println!("Unexpected kind {} in write_functag.", kind);
+                writer.unexpected(&child, "write_functag");
             }
         }
     }
@@ -47,12 +59,17 @@ pub fn write_funcenum(node: Node, writer: &mut Writer) -> Result<(), Utf8Error>
 
     if !prev_kind.starts_with("preproc_") && prev_kind != "" && prev_kind != "comment" {
         // Insert new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
-    let mut cursor = node.walk();
+    if writer.settings.convert_old_syntax {
+        if has_convertible_tags(&node, writer)? {
+            return write_converted_funcenum(node, writer);
+        }
+        writer.unconverted(&node, "write_funcenum");
+    }
 
-    for child in node.children(&mut cursor) {
+    write_children(&node, writer, |child, writer| {
         let kind = child.kind();
         match kind.borrow() {
             "funcenum" => writer.output.push_str("funcenum "),
@@ -80,12 +97,14 @@ pub fn write_funcenum(node: Node, writer: &mut Writer) -> Result<(), Utf8Error>
                 }
             }
             "comment" => write_comment(&child, writer)?,
-            ";" | "," => continue,
+            ";" | "," => {}
             _ => {
-                println!("Unexpected kind {} in write_funcenum.", kind);
+                writer.unexpected(&child, "write_funcenum");
             }
         }
-    }
+
+        Ok(())
+    })?;
     writer.output.push(';');
     writer.breakl();
 
@@ -104,10 +123,156 @@ fn write_funcenum_member(node: Node, writer: &mut Writer) -> Result<(), Utf8Erro
             "old_type" => write_old_type(child, writer)?,
             "argument_declarations" => write_argument_declarations(child, writer)?,
             _ => {
-                println!("Unexpected kind {} in write_funcenum_member.", kind);
+                writer.unexpected(&child, "write_funcenum_member");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a functag converted to a typedef, e.g. `functag public Foo(Float:x);` to
+/// `typedef Foo = function int (float x);`. An untagged functag returns an `int`.
+///
+/// # Arguments
+///
+/// * `node`   - The functag node to write.
+/// * `writer` - The writer object.
+fn write_converted_functag(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
+    let mut cursor = node.walk();
+    let mut return_type = "int".to_string();
+    let mut name = None;
+    let mut arguments = None;
+
+    // The tag can come before or after the name, it is collected first.
+    for child in node.children(&mut cursor) {
+        let kind = child.kind();
+        match kind.borrow() {
+            "old_type" => return_type = converted_return_type(&child, writer)?,
+            "symbol" => name = Some(child),
+            "argument_declarations" => arguments = Some(child),
+            "functag" | "public" | ";" => continue,
+            _ => {
+                writer.unexpected(&child, "write_converted_functag");
             }
         }
     }
 
+    writer.output.push_str("typedef ");
+    if let Some(name) = name {
+        write_node(&name, writer)?;
+    }
+    writer.output.push_str(" = function ");
+    writer.output.push_str(&return_type);
+    writer.output.push(' ');
+    if let Some(arguments) = arguments {
+        write_converted_argument_declarations(arguments, writer)?;
+    }
+    writer.output.push(';');
+    writer.breakl();
+
     Ok(())
 }
+
+/// Write a funcenum converted to a typeset, with the layout of the typesets.
+///
+/// # Arguments
+///
+/// * `node`   - The funcenum node to write.
+/// * `writer` - The writer object.
+fn write_converted_funcenum(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
+    write_children(&node, writer, |child, writer| {
+        let kind = child.kind();
+        match kind.borrow() {
+            "funcenum" => writer.output.push_str("typeset "),
+            "symbol" => write_node(&child, writer)?,
+            "{" | "}" => write_typeset_brace(&child, writer),
+            "funcenum_member" => {
+                let next_kind = next_sibling_kind(&child);
+                write_converted_funcenum_member(child, writer)?;
+                writer.output.push(';');
+
+                if next_kind != "" {
+                    writer.breakl();
+                }
+            }
+            "comment" => write_comment(&child, writer)?,
+            ";" | "," => {}
+            _ => {
+                writer.unexpected(&child, "write_converted_funcenum");
+            }
+        }
+
+        Ok(())
+    })?;
+    writer.output.push(';');
+    writer.breakl();
+
+    Ok(())
+}
+
+/// Write a funcenum member converted to the function type of a typeset, e.g.
+/// `Action:public(Handle:timer)` to `function Action (Handle timer)`.
+///
+/// # Arguments
+///
+/// * `node`   - The funcenum member node to write.
+/// * `writer` - The writer object.
+fn write_converted_funcenum_member(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
+    let mut cursor = node.walk();
+    let mut return_type = "int".to_string();
+
+    writer.write_indent();
+    writer.output.push_str("function ");
+
+    for child in node.children(&mut cursor) {
+        let kind = child.kind();
+        match kind.borrow() {
+            "public" => continue,
+            "old_type" => return_type = converted_return_type(&child, writer)?,
+            "argument_declarations" => {
+                writer.output.push_str(&return_type);
+                writer.output.push(' ');
+                write_converted_argument_declarations(child, writer)?;
+            }
+            _ => {
+                writer.unexpected(&child, "write_converted_funcenum_member");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the new syntax type of the tag of a functag or a funcenum member.
+///
+/// # Arguments
+///
+/// * `node`   - The old type node.
+/// * `writer` - The writer object.
+fn converted_return_type(node: &Node, writer: &Writer) -> Result<String, Utf8Error> {
+    let tag = node.utf8_text(writer.source)?;
+
+    Ok(convert_old_tag(&tag).unwrap_or_else(|| tag.to_string()))
+}
+
+/// Returns true if all the tags of a node and of its descendants have an
+/// equivalent new syntax type.
+///
+/// # Arguments
+///
+/// * `node`   - The node to check.
+/// * `writer` - The writer object.
+fn has_convertible_tags(node: &Node, writer: &Writer) -> Result<bool, Utf8Error> {
+    if node.kind() == "old_type" {
+        return Ok(convert_old_tag(&node.utf8_text(writer.source)?).is_some());
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if !has_convertible_tags(&child, writer)? {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
@@ -2,9 +2,14 @@ use std::{borrow::Borrow, str::Utf8Error};
 
 use tree_sitter::Node;
 
+use crate::doc::Doc;
+
 use super::{
-    expressions::{write_expression, write_old_type},
-    next_sibling_kind, prev_sibling_kind,
+    expressions::{build_comment, build_operand, write_expression, write_old_type},
+    next_sibling_kind,
+    old_variables::convert_old_tag,
+    precedence::ASSIGNMENT,
+    prev_sibling_kind,
     statements::{write_block, write_statement},
     variables::write_type,
     write_dimension, write_fixed_dimension, write_node, Writer,
@@ -16,7 +21,7 @@ pub fn write_function_declaration(node: Node, writer: &mut Writer) -> Result<(),
 
     if !prev_kind.starts_with("preproc_") && prev_kind != "" && prev_kind != "comment" {
         // Insert two new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
     let mut cursor = node.walk();
@@ -51,7 +56,7 @@ pub fn write_function_definition(node: Node, writer: &mut Writer) -> Result<(),
 
     if !prev_kind.starts_with("preproc_") && prev_kind != "" && prev_kind != "comment" {
         // Insert two new lines automatically:
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
     let mut cursor = node.walk();
@@ -70,39 +75,266 @@ pub fn write_function_definition(node: Node, writer: &mut Writer) -> Result<(),
     Ok(())
 }
 
+/// Write the argument declarations of a function. If they do not fit on the line,
+/// they are broken one per line, with the closing parenthesis on its own line,
+/// and the names are aligned in a column when `align_argument_names` is set.
+///
+/// # Arguments
+///
+/// * `node`   - The argument declarations node to write.
+/// * `writer` - The writer object.
 pub fn write_argument_declarations(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
+    write_arguments(node, writer, false)
+}
+
+/// Write the old syntax argument declarations of a functag or a funcenum converted
+/// to the new syntax, e.g. `Float:x` to `float x`.
+///
+/// # Arguments
+///
+/// * `node`   - The argument declarations node to write.
+/// * `writer` - The writer object.
+pub fn write_converted_argument_declarations(
+    node: Node,
+    writer: &mut Writer,
+) -> Result<(), Utf8Error> {
+    write_arguments(node, writer, true)
+}
+
+/// Write argument declarations, converting them to the new syntax if `convert` is set.
+///
+/// # Arguments
+///
+/// * `node`    - The argument declarations node to write.
+/// * `writer`  - The writer object.
+/// * `convert` - Whether or not to convert the old syntax arguments.
+fn write_arguments(node: Node, writer: &mut Writer, convert: bool) -> Result<(), Utf8Error> {
     let mut cursor = node.walk();
+    // Each argument, followed by its comma and its trailing comments.
+    let mut arguments: Vec<Vec<Doc>> = vec![];
+    // The index of each argument in its documents, and its type and name if it
+    // can be aligned.
+    let mut parts: Vec<(usize, Option<(String, String)>)> = vec![];
+    let mut leading_comments = vec![];
 
     for child in node.children(&mut cursor) {
-        match child.kind().borrow() {
-            "(" | ")" => write_node(&child, writer)?,
+        let (argument, argument_parts, default_value) = match child.kind().borrow() {
+            "(" | ")" => continue,
+            "," => {
+                if let Some(argument) = arguments.last_mut() {
+                    argument.push(Doc::text(","));
+                }
+                continue;
+            }
+            "comment" => {
+                let comment = build_comment(&child, writer)?;
+                match arguments.last_mut() {
+                    Some(argument) => {
+                        argument.push(Doc::text(" "));
+                        argument.push(comment);
+                    }
+                    None => {
+                        leading_comments.push(comment);
+                        leading_comments.push(Doc::text(" "));
+                    }
+                }
+                continue;
+            }
+            "rest_argument" if convert => (converted_rest_argument(child, writer)?, None, None),
             "rest_argument" => {
+                let text = writer.capture(|writer| write_rest_argument(child, writer))?;
+                (text, None, None)
+            }
+            "argument_declaration" => {
+                let (type_, name, default_value) = if convert {
+                    converted_argument_declaration_parts(child, writer)?
+                } else {
+                    argument_declaration_parts(child, writer)?
+                };
+                let text = format!("{}{}", type_, name);
+                // Only the arguments whose type is separated from their name are aligned.
+                let argument_parts = match type_.strip_suffix(' ') {
+                    Some(type_) if !name.is_empty() => Some((type_.to_string(), name)),
+                    _ => None,
+                };
+                (text, argument_parts, default_value)
+            }
+            _ => (
+                writer.capture(|writer| write_node(&child, writer))?,
+                None,
+                None,
+            ),
+        };
+        let mut docs = std::mem::take(&mut leading_comments);
+        parts.push((docs.len(), argument_parts));
+        docs.push(Doc::text(argument));
+        if let Some(default_value) = default_value {
+            docs.push(Doc::text(" = "));
+            docs.push(default_value);
+        }
+        arguments.push(docs);
+    }
+    if arguments.is_empty() {
+        if leading_comments.is_empty() {
+            writer.output.push_str("()");
+            return Ok(());
+        }
+        // Only comments, drop the space after the last one.
+        leading_comments.pop();
+        arguments.push(leading_comments);
+    }
+
+    if writer.settings.align_argument_names {
+        let type_width = parts
+            .iter()
+            .filter_map(|(_, parts)| parts.as_ref())
+            .map(|(type_, _)| type_.chars().count())
+            .max()
+            .unwrap_or(0);
+        for (argument, (index, parts)) in arguments.iter_mut().zip(parts.into_iter()) {
+            if let Some((type_, name)) = parts {
+                // Only align the names when the arguments are broken one per line.
+                let aligned = format!("{:width$} {}", type_, name, width = type_width);
+                let flat = std::mem::replace(&mut argument[index], Doc::text(""));
+                argument[index] = Doc::if_break(Doc::text(aligned), flat);
+            }
+        }
+    }
+
+    let arguments: Vec<Doc> = arguments.into_iter().map(Doc::concat).collect();
+    writer.write_doc(&Doc::group(Doc::concat(vec![
+        Doc::text("("),
+        Doc::indent(Doc::concat(vec![
+            Doc::SoftLine,
+            Doc::join(arguments, Doc::Line),
+        ])),
+        Doc::SoftLine,
+        Doc::text(")"),
+    ])));
+
+    Ok(())
+}
+
+/// Returns the type and the name of an argument declaration as they are written,
+/// and the document of its default value, if it has one. The name includes its
+/// dimensions.
+///
+/// # Arguments
+///
+/// * `node`   - The argument declaration node.
+/// * `writer` - The writer object.
+fn argument_declaration_parts(
+    node: Node,
+    writer: &mut Writer,
+) -> Result<(String, String, Option<Doc>), Utf8Error> {
+    let mut name_start = None;
+    let text = writer.capture(|writer| {
+        name_start = write_argument_declaration(node, writer)?;
+        Ok(())
+    })?;
+    let (type_, name) = text.split_at(name_start.unwrap_or(text.len()));
+    let mut cursor = node.walk();
+    let default_value = node
+        .children(&mut cursor)
+        .skip_while(|child| child.kind() != "=")
+        .nth(1)
+        .map(|value| build_operand(value, ASSIGNMENT, writer))
+        .transpose()?;
+
+    Ok((type_.to_string(), name.to_string(), default_value))
+}
+
+/// Returns the type and the name of an old syntax argument declaration converted
+/// to the new syntax, e.g. `float ` and `&x` for `&Float:x`, and the document of
+/// its default value. The arguments which already have a new syntax type are
+/// returned as they are written.
+///
+/// # Arguments
+///
+/// * `node`   - The argument declaration node.
+/// * `writer` - The writer object.
+fn converted_argument_declaration_parts(
+    node: Node,
+    writer: &mut Writer,
+) -> Result<(String, String, Option<Doc>), Utf8Error> {
+    let (type_, name, default_value) = argument_declaration_parts(node, writer)?;
+    let mut is_const = false;
+    let mut by_reference = false;
+    let mut tag = None;
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind().borrow() {
+            "const" => is_const = true,
+            "argument_type" => {
                 let mut sub_cursor = child.walk();
                 for sub_child in child.children(&mut sub_cursor) {
                     match sub_child.kind().borrow() {
-                        "type" => write_node(&sub_child, writer)?,
-                        "old_type" => write_old_type(sub_child, writer)?,
-                        _ => write_node(&sub_child, writer)?,
+                        "&" => by_reference = true,
+                        "old_type" => tag = Some(sub_child.utf8_text(writer.source)?),
+                        "type" => return Ok((type_, name, default_value)),
+                        _ => continue,
                     }
                 }
             }
-            "argument_declaration" => write_argument_declaration(child, writer)?,
-            "," => writer.output.push_str(", "),
-            _ => write_node(&child, writer)?,
+            _ => continue,
         }
     }
+    let new_type = match tag {
+        // Keep the tags without an equivalent type, the caller reports them.
+        Some(tag) => convert_old_tag(&tag).unwrap_or_else(|| tag.to_string()),
+        None => "int".to_string(),
+    };
+    let type_ = format!("{}{} ", if is_const { "const " } else { "" }, new_type);
+    if by_reference {
+        return Ok((type_, format!("&{}", name), default_value));
+    }
 
-    Ok(())
+    Ok((type_, name, default_value))
+}
+
+/// Returns an old syntax rest argument converted to the new syntax, e.g. `any ...`
+/// for `any:...`.
+///
+/// # Arguments
+///
+/// * `node`   - The rest argument node.
+/// * `writer` - The writer object.
+fn converted_rest_argument(node: Node, writer: &mut Writer) -> Result<String, Utf8Error> {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind().borrow() {
+            "old_type" => {
+                let tag = child.utf8_text(writer.source)?;
+                let new_type = convert_old_tag(&tag).unwrap_or_else(|| tag.to_string());
+                return Ok(format!("{} ...", new_type));
+            }
+            "type" => return writer.capture(|writer| write_rest_argument(node, writer)),
+            _ => continue,
+        }
+    }
+
+    Ok("int ...".to_string())
 }
 
-fn write_argument_declaration(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
+/// Write an argument declaration, and return the position of its name in the
+/// output, if it has one.
+///
+/// # Arguments
+///
+/// * `node`   - The argument declaration node to write.
+/// * `writer` - The writer object.
+fn write_argument_declaration(node: Node, writer: &mut Writer) -> Result<Option<usize>, Utf8Error> {
     let mut cursor = node.walk();
+    let mut name_start = None;
 
     for child in node.children(&mut cursor) {
         match child.kind().borrow() {
             "const" => writer.output.push_str("const "),
             "argument_type" => write_argument_type(child, writer)?,
-            "symbol" => write_node(&child, writer)?,
+            "symbol" => {
+                name_start.get_or_insert(writer.output.len());
+                write_node(&child, writer)?
+            }
             "dimension" => write_dimension(child, writer, true)?,
             "fixed_dimension" => {
                 let next_kind = next_sibling_kind(&child);
@@ -111,11 +343,31 @@ fn write_argument_declaration(node: Node, writer: &mut Writer) -> Result<(), Utf
                     writer.output.push(' ')
                 };
             }
-            "=" => writer.output.push_str(" = "),
+            // The default value is built by the caller.
+            "=" => break,
             _ => write_expression(child, writer)?,
         }
     }
 
+    Ok(name_start)
+}
+
+/// Write a rest argument, like `any ...`.
+///
+/// # Arguments
+///
+/// * `node`   - The rest argument node to write.
+/// * `writer` - The writer object.
+fn write_rest_argument(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind().borrow() {
+            "type" => write_node(&child, writer)?,
+            "old_type" => write_old_type(child, writer)?,
+            _ => write_node(&child, writer)?,
+        }
+    }
+
     Ok(())
 }
 
@@ -11,7 +11,7 @@ pub fn write_hardcoded_symbol(node: &Node, writer: &mut Writer) -> Result<(), Ut
         match kind.borrow() {
             "using __intrinsics__.Handle" => write_node(&child, writer)?,
             ";" => continue,
-            _ => println!("Unexpected kind {} in write_hardcoded_symbol.", kind),
+            _ => writer.unexpected(&child, "write_hardcoded_symbol"),
         }
     }
     writer.output.push(';');
@@ -4,7 +4,7 @@ use tree_sitter::Node;
 
 use super::{
     functions::write_argument_declarations, prev_sibling_kind, statements::write_block,
-    variables::write_type, write_comment, write_node, Writer,
+    variables::write_type, write_children, write_comment, write_node, Writer,
 };
 
 pub fn write_methodmap(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
@@ -17,12 +17,10 @@ pub fn write_methodmap(node: Node, writer: &mut Writer) -> Result<(), Utf8Error>
         && prev_kind != "alias_declaration"
     {
         // Insert new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
-    let mut cursor = node.walk();
-
-    for child in node.children(&mut cursor) {
+    write_children(&node, writer, |child, writer| {
         let kind = child.kind();
         match kind.borrow() {
             "methodmap" => writer.output.push_str("methodmap "),
@@ -51,12 +49,14 @@ pub fn write_methodmap(node: Node, writer: &mut Writer) -> Result<(), Utf8Error>
             }
             "methodmap_property" => write_methodmap_property(child, writer)?,
             "comment" => write_comment(&child, writer)?,
-            ";" => continue,
+            ";" => {}
             _ => {
-                println!("Unexpected kind {} in write_methodmap.", kind);
+                writer.unexpected(&child, "write_methodmap");
             }
         }
-    }
+
+        Ok(())
+    })?;
     writer.output.push(';');
     writer.breakl();
 
@@ -69,7 +69,7 @@ fn write_methodmap_alias(node: Node, writer: &mut Writer) -> Result<(), Utf8Erro
 
     if !prev_kind.starts_with("preproc_") && prev_kind != "" && prev_kind != "comment" {
         // Insert two new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
     let mut cursor = node.walk();
@@ -83,7 +83,7 @@ fn write_methodmap_alias(node: Node, writer: &mut Writer) -> Result<(), Utf8Erro
             "~" | "(" | ")" | "symbol" => write_node(&child, writer)?,
             "=" => writer.output.push_str(" = "),
             ";" => continue,
-            _ => println!("Unexpected kind {} in write_alias_declaration.", kind),
+            _ => writer.unexpected(&child, "write_methodmap_alias"),
         }
     }
     writer.output.push(';');
@@ -98,7 +98,7 @@ fn write_methodmap_native(node: Node, writer: &mut Writer) -> Result<(), Utf8Err
 
     if !prev_kind.starts_with("preproc_") && prev_kind != "" && prev_kind != "comment" {
         // Insert two new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
     let mut cursor = node.walk();
@@ -118,7 +118,7 @@ fn write_methodmap_native(node: Node, writer: &mut Writer) -> Result<(), Utf8Err
             "=" => writer.output.push_str(" = "),
             "argument_declarations" => write_argument_declarations(child, writer)?,
             ";" => continue,
-            _ => println!("Unexpected kind {} in write_methodmap_native.", kind),
+            _ => writer.unexpected(&child, "write_methodmap_native"),
         }
     }
     writer.output.push(';');
@@ -133,7 +133,7 @@ fn write_methodmap_method(node: Node, writer: &mut Writer) -> Result<(), Utf8Err
 
     if !prev_kind.starts_with("preproc_") && prev_kind != "" && prev_kind != "comment" {
         // Insert two new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
     let mut cursor = node.walk();
@@ -161,7 +161,7 @@ fn write_methodmap_method(node: Node, writer: &mut Writer) -> Result<(), Utf8Err
                     write_block(child, writer, false)?;
                 }
             }
-            _ => println!("Unexpected kind {} in write_methodmap_method.", kind),
+            _ => writer.unexpected(&child, "write_methodmap_method"),
         }
     }
     writer.breakl();
@@ -175,14 +175,12 @@ fn write_methodmap_property(node: Node, writer: &mut Writer) -> Result<(), Utf8E
 
     if !prev_kind.starts_with("preproc_") && prev_kind != "" && prev_kind != "comment" {
         // Insert two new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
-    let mut cursor = node.walk();
-
     writer.write_indent();
 
-    for child in node.children(&mut cursor) {
+    write_children(&node, writer, |child, writer| {
         let kind = child.kind();
         match kind.borrow() {
             "property" => {
@@ -210,10 +208,13 @@ fn write_methodmap_property(node: Node, writer: &mut Writer) -> Result<(), Utf8E
             "methodmap_property_method" | "methodmap_property_native" => {
                 write_methodmap_property_method(child, writer)?
             }
-            ";" => continue,
-            _ => println!("Unexpected kind {} in write_methodmap_property.", kind),
+            "comment" => write_comment(&child, writer)?,
+            ";" => {}
+            _ => writer.unexpected(&child, "write_methodmap_property"),
         }
-    }
+
+        Ok(())
+    })?;
     writer.output.push(';');
     writer.breakl();
 
@@ -226,7 +227,7 @@ fn write_methodmap_property_alias(node: Node, writer: &mut Writer) -> Result<(),
 
     if !prev_kind.starts_with("preproc_") && prev_kind != "" && prev_kind != "comment" {
         // Insert two new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
     let mut cursor = node.walk();
@@ -241,10 +242,7 @@ fn write_methodmap_property_alias(node: Node, writer: &mut Writer) -> Result<(),
             "symbol" => write_node(&child, writer)?,
             "=" => writer.output.push_str(" = "),
             ";" => continue,
-            _ => println!(
-                "Unexpected kind {} in write_methodmap_property_alias.",
-                kind
-            ),
+            _ => writer.unexpected(&child, "write_methodmap_property_alias"),
         }
     }
     writer.output.push(';');
@@ -259,7 +257,7 @@ fn write_methodmap_property_method(node: Node, writer: &mut Writer) -> Result<()
 
     if !prev_kind.starts_with("preproc_") && prev_kind != "" && prev_kind != "comment" {
         // Insert two new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
     let mut cursor = node.walk();
@@ -288,10 +286,7 @@ fn write_methodmap_property_method(node: Node, writer: &mut Writer) -> Result<()
                 }
             }
             ";" => writer.output.push(';'),
-            _ => println!(
-                "Unexpected kind {} in write_methodmap_property_method.",
-                kind
-            ),
+            _ => writer.unexpected(&child, "write_methodmap_property_method"),
         }
     }
     writer.breakl();
@@ -309,10 +304,7 @@ fn write_methodmap_property_setter(node: Node, writer: &mut Writer) -> Result<()
             "symbol" | "(" | ")" => write_node(&child, writer)?,
             "type" => write_type(&child, writer)?,
             ";" => continue,
-            _ => println!(
-                "Unexpected kind {} in write_methodmap_property_setter.",
-                kind
-            ),
+            _ => writer.unexpected(&child, "write_methodmap_property_setter"),
         }
     }
 
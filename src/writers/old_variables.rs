@@ -2,6 +2,9 @@ use super::{
     expressions::{write_expression, write_old_type},
     next_sibling_kind, node_len,
     preproc::insert_break,
+    variables::{
+        write_converted_global_variable_declaration, write_converted_variable_declaration_statement,
+    },
     write_comment, write_dimension, write_fixed_dimension, write_node, Writer,
 };
 use std::{borrow::Borrow, str::Utf8Error};
@@ -18,28 +21,40 @@ pub fn write_old_global_variable_declaration(
     node: &Node,
     writer: &mut Writer,
 ) -> Result<(), Utf8Error> {
+    if writer.settings.convert_old_syntax {
+        match new_syntax_type(node, writer)? {
+            Some(type_) => {
+                return write_converted_global_variable_declaration(node, writer, &type_)
+            }
+            None => writer.unconverted(node, "write_old_global_variable_declaration"),
+        }
+    }
     let mut cursor = node.walk();
 
-    let should_break = should_break_declaration(&node)?;
+    let should_break = should_break_declaration(&node, writer)?;
 
-    let mut declarator_length = 0;
+    // Keep track of the column of the first declaration to properly
+    // align line break variables.
+    let mut declaration_column = 0;
     for child in node.children(&mut cursor) {
         let kind = child.kind();
         match kind.borrow() {
             "variable_storage_class" | "variable_visibility" | "new" | "decl" => {
                 write_node(&child, writer)?;
                 writer.output.push(' ');
-                declarator_length += node_len(&child) + 1;
             }
             "comment" => {
                 write_comment(&child, writer)?;
                 if should_break {
-                    writer
-                        .output
-                        .push_str(" ".repeat(declarator_length).as_str());
+                    writer.align_to(declaration_column);
                 }
             }
-            "old_variable_declaration" => write_old_variable_declaration(child, writer)?,
+            "old_variable_declaration" => {
+                if declaration_column == 0 {
+                    declaration_column = writer.column();
+                }
+                write_old_variable_declaration(child, writer)?
+            }
             "," => {
                 if should_break {
                     let next_kind = next_sibling_kind(&child);
@@ -47,19 +62,14 @@ pub fn write_old_global_variable_declaration(
                         writer.output.push_str(",");
                     } else {
                         writer.output.push_str(",\n");
-                        writer
-                            .output
-                            .push_str(" ".repeat(declarator_length).as_str());
+                        writer.align_to(declaration_column);
                     }
                 } else {
                     writer.output.push_str(", ")
                 }
             }
             ";" => continue,
-            _ => println!(
-                "Unexpected kind {} in write_old_global_variable_declaration.",
-                kind
-            ),
+            _ => writer.unexpected(&child, "write_old_global_variable_declaration"),
         }
     }
     writer.output.push(';');
@@ -69,12 +79,92 @@ pub fn write_old_global_variable_declaration(
     Ok(())
 }
 
+/// Returns the new syntax type of the variables of an old declaration, or `None`
+/// if the declaration cannot be converted safely: when the variables have different
+/// tags, when one of the tags has no equivalent type, or when an untagged array
+/// may hold a string.
+///
+/// # Arguments
+///
+/// * `node`   - The node which has the old variable declarations.
+/// * `writer` - The writer object.
+fn new_syntax_type(node: &Node, writer: &Writer) -> Result<Option<String>, Utf8Error> {
+    let mut type_: Option<String> = None;
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if child.kind() != "old_variable_declaration" {
+            continue;
+        }
+        let mut tag = None;
+        let mut is_array = false;
+        let mut is_initialized = false;
+        let mut is_string = false;
+        let mut sub_cursor = child.walk();
+        for sub_child in child.children(&mut sub_cursor) {
+            match sub_child.kind().borrow() {
+                "old_type" => tag = Some(sub_child.utf8_text(writer.source)?),
+                "dimension" | "fixed_dimension" => is_array = true,
+                "=" => is_initialized = true,
+                "string_literal" => is_string = true,
+                _ => continue,
+            }
+        }
+        let child_type = match tag {
+            Some(tag) => match convert_old_tag(&tag) {
+                Some(child_type) => child_type,
+                None => return Ok(None),
+            },
+            // An untagged array can hold a string in the old syntax, but not an
+            // `int` array in the new syntax. Without an initializer, an array
+            // passed to a `char[]` argument is a string too.
+            None if is_string || (is_array && !is_initialized) => return Ok(None),
+            None => "int".to_string(),
+        };
+        match &type_ {
+            Some(type_) if *type_ != child_type => return Ok(None),
+            Some(_) => continue,
+            None => type_ = Some(child_type),
+        }
+    }
+
+    Ok(type_)
+}
+
+/// Returns the new syntax type of an old tag, like `float` for `Float:`, or `None`
+/// if it has no equivalent, like the tag lists (`{Float, _}:`).
+///
+/// # Arguments
+///
+/// * `tag` - The text of the old type node.
+pub fn convert_old_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().strip_suffix(':')?.trim_end();
+    let type_ = match tag {
+        "_" => "int",
+        "Float" => "float",
+        "String" => "char",
+        _ => {
+            let mut chars = tag.chars();
+            let is_symbol = chars
+                .next()
+                .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !is_symbol {
+                return None;
+            }
+            tag
+        }
+    };
+
+    Some(type_.to_string())
+}
+
 /// Computes if we should break after each `,` in an old variable declaration.
 ///
 /// # Arguments
 ///
 /// * `node`   - The node which has the variable declarations.
-fn should_break_declaration(node: &Node) -> Result<bool, Utf8Error> {
+/// * `writer` - The writer object.
+fn should_break_declaration(node: &Node, writer: &Writer) -> Result<bool, Utf8Error> {
     let mut cursor = node.walk();
 
     // Compute an estimated length of the declarations.
@@ -117,7 +207,7 @@ fn should_break_declaration(node: &Node) -> Result<bool, Utf8Error> {
         }
     }
 
-    if length <= 80 && !nested_comment {
+    if !writer.exceeds_line_width(length) && !nested_comment {
         return Ok(false);
     }
 
@@ -135,15 +225,27 @@ pub fn write_old_variable_declaration_statement(
     writer: &mut Writer,
     do_indent: bool,
 ) -> Result<(), Utf8Error> {
+    if writer.settings.convert_old_syntax {
+        match new_syntax_type(&node, writer)? {
+            Some(type_) => {
+                return write_converted_variable_declaration_statement(
+                    node, writer, do_indent, &type_,
+                )
+            }
+            None => writer.unconverted(&node, "write_old_variable_declaration_statement"),
+        }
+    }
     let mut cursor = node.walk();
 
-    let should_break = should_break_declaration(&node)?;
+    let should_break = should_break_declaration(&node, writer)?;
 
     if do_indent {
         writer.write_indent();
     }
 
-    let mut declarator_length = 0;
+    // Keep track of the column of the first declaration to properly
+    // align line break variables.
+    let mut declaration_column = 0;
 
     for child in node.children(&mut cursor) {
         let kind = child.kind();
@@ -151,18 +253,17 @@ pub fn write_old_variable_declaration_statement(
             "variable_storage_class" | "new" | "decl" => {
                 write_node(&child, writer)?;
                 writer.output.push(' ');
-                declarator_length += node_len(&child) + 1;
             }
-            "old_variable_declaration" => write_old_variable_declaration(child, writer)?,
+            "old_variable_declaration" => {
+                if declaration_column == 0 {
+                    declaration_column = writer.column();
+                }
+                write_old_variable_declaration(child, writer)?
+            }
             "comment" => {
                 write_comment(&child, writer)?;
                 if should_break {
-                    if do_indent {
-                        writer.write_indent();
-                    }
-                    writer
-                        .output
-                        .push_str(" ".repeat(declarator_length).as_str());
+                    writer.align_to(declaration_column);
                 }
             }
             "," => {
@@ -172,12 +273,7 @@ pub fn write_old_variable_declaration_statement(
                         writer.output.push_str(",");
                     } else {
                         writer.output.push_str(",\n");
-                        if do_indent {
-                            writer.write_indent();
-                        }
-                        writer
-                            .output
-                            .push_str(" ".repeat(declarator_length).as_str());
+                        writer.align_to(declaration_column);
                     }
                 } else {
                     writer.output.push_str(", ")
@@ -217,10 +313,7 @@ fn write_old_variable_declaration(node: Node, writer: &mut Writer) -> Result<(),
                 if writer.is_expression(&kind) {
                     write_expression(child, writer)?;
                 } else {
-                    println!(
-                        "Unexpected kind {} in write_old_variable_declaration.",
-                        kind
-                    )
+                    writer.unexpected(&child, "write_old_variable_declaration")
                 }
             }
         }
@@ -0,0 +1,168 @@
+//! Precedence and associativity of the SourcePawn operators, used to know when
+//! the parentheses of an expression can be removed, or should be added for clarity.
+//!
+//! The levels follow the compiler: unlike C, the bitwise operators bind tighter
+//! than the relational operators, and the relational operators can be chained
+//! (`a < b < c` means `a < b && b < c`).
+
+use std::borrow::Borrow;
+
+use tree_sitter::Node;
+
+/// Precedence of a comma expression, the lowest.
+pub const COMMA: u8 = 0;
+/// Precedence of the assignment operators, which are right associative.
+pub const ASSIGNMENT: u8 = 1;
+/// Precedence of the ternary operator, which is right associative.
+pub const TERNARY: u8 = 2;
+/// Precedence of the prefix operators and of the old type casts.
+pub const UNARY: u8 = 13;
+/// Precedence of the primary expressions: symbols, literals, calls, accesses and
+/// parenthesized expressions.
+pub const PRIMARY: u8 = 14;
+
+/// How the operators of a same level are grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    /// `a - b - c` is `(a - b) - c`.
+    Left,
+    /// The operators cannot be regrouped, like the chained relational operators.
+    None,
+}
+
+/// Family of a binary operator, used to find the mixes of operators which are
+/// easy to misread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperatorGroup {
+    Arithmetic,
+    Shift,
+    Bitwise,
+    Comparison,
+    Logical,
+}
+
+/// Precedence, associativity and family of a binary operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinaryOperator {
+    pub precedence: u8,
+    pub associativity: Associativity,
+    pub group: OperatorGroup,
+}
+
+/// Returns the precedence, the associativity and the family of a binary operator,
+/// or `None` if the operator is unknown.
+///
+/// # Arguments
+///
+/// * `operator` - The text of the operator.
+pub fn binary_operator(operator: &str) -> Option<BinaryOperator> {
+    use Associativity::{Left, None as NonAssociative};
+    use OperatorGroup::*;
+
+    let (precedence, associativity, group) = match operator {
+        "*" | "/" | "%" => (12, Left, Arithmetic),
+        "+" | "-" => (11, Left, Arithmetic),
+        "<<" | ">>" | ">>>" => (10, Left, Shift),
+        "&" => (9, Left, Bitwise),
+        "^" => (8, Left, Bitwise),
+        "|" => (7, Left, Bitwise),
+        "<" | "<=" | ">" | ">=" => (6, NonAssociative, Comparison),
+        "==" | "!=" => (5, Left, Comparison),
+        "&&" => (4, Left, Logical),
+        "||" => (3, Left, Logical),
+        _ => return None,
+    };
+
+    Some(BinaryOperator {
+        precedence,
+        associativity,
+        group,
+    })
+}
+
+/// Returns the operator of a binary expression node, or `None` if the node is
+/// not a binary expression or its operator is unknown.
+///
+/// # Arguments
+///
+/// * `node` - The expression node.
+pub fn node_operator(node: &Node) -> Option<BinaryOperator> {
+    if node.kind() != "binary_expression" {
+        return None;
+    }
+    node.child_by_field_name("operator")
+        .and_then(|operator| binary_operator(&operator.kind()))
+}
+
+/// Returns true if an operand mixes its operator with the operator of its parent
+/// in a way which is easy to misread without parentheses: `&&` within `||`, or
+/// bitwise and comparison operators (`x & MASK == 0`).
+///
+/// # Arguments
+///
+/// * `parent`  - The operator of the binary expression.
+/// * `operand` - The operator of the operand.
+pub fn needs_clarifying_parentheses(parent: &BinaryOperator, operand: &BinaryOperator) -> bool {
+    use OperatorGroup::*;
+
+    match (parent.group, operand.group) {
+        (Logical, Logical) => parent.precedence != operand.precedence,
+        (Bitwise, Comparison) | (Comparison, Bitwise) => true,
+        _ => false,
+    }
+}
+
+/// Returns the precedence of an expression node. Unknown kinds get the lowest
+/// precedence, so that their parentheses are always kept.
+///
+/// # Arguments
+///
+/// * `node` - The expression node.
+pub fn precedence(node: &Node) -> u8 {
+    match node.kind().borrow() {
+        "symbol"
+        | "null"
+        | "this"
+        | "int_literal"
+        | "bool_literal"
+        | "char_literal"
+        | "float_literal"
+        | "string_literal"
+        | "function_call"
+        | "field_access"
+        | "array_indexed_access"
+        | "scope_access"
+        | "view_as"
+        | "sizeof_expression"
+        | "new_instance"
+        | "parenthesized_expression" => PRIMARY,
+        "unary_expression" | "update_expression" | "old_type_cast" => UNARY,
+        "binary_expression" => node_operator(node).map_or(COMMA, |operator| operator.precedence),
+        "ternary_expression" => TERNARY,
+        "assignment_expression" => ASSIGNMENT,
+        _ => COMMA,
+    }
+}
+
+/// Returns the minimum precedence the operands of a binary expression must have
+/// to be written without parentheses, for the left and the right operand.
+///
+/// # Arguments
+///
+/// * `node` - The binary expression node.
+pub fn binary_operand_precedences(node: &Node) -> (u8, u8) {
+    match node_operator(node) {
+        Some(BinaryOperator {
+            precedence,
+            associativity: Associativity::Left,
+            ..
+        }) => (precedence, precedence + 1),
+        Some(BinaryOperator {
+            precedence,
+            associativity: Associativity::None,
+            ..
+        }) => (precedence + 1, precedence + 1),
+        // Keep the parentheses around the operands of unknown operators.
+        None => (PRIMARY + 1, PRIMARY + 1),
+    }
+}
@@ -56,7 +56,7 @@ pub fn write_preproc_include(node: &Node, writer: &mut Writer) -> Result<(), Utf
                 writer.output.push(' ')
             }
             "string_literal" | "system_lib_string" => write_node(&child, writer)?,
-            _ => println!("Unexpected kind {} in write_preproc_include.", kind),
+            _ => writer.unexpected(&child, "write_preproc_include"),
         }
     }
 
@@ -86,7 +86,7 @@ pub fn write_preproc_define(node: &Node, writer: &mut Writer) -> Result<(), Utf8
             "," => writer.output.push_str(", "),
             "(" | ")" => write_node(&child, writer)?,
             "macro_param" => write_node(&child, writer)?,
-            _ => println!("Unexpected kind {} in write_preproc_define.", kind),
+            _ => writer.unexpected(&child, "write_preproc_define"),
         }
     }
 
@@ -109,7 +109,7 @@ pub fn write_preproc_undefine(node: &Node, writer: &mut Writer) -> Result<(), Ut
         match kind.borrow() {
             "symbol" => write_node(&child, writer)?,
             "#undef" => writer.output.push_str("#undef "),
-            _ => println!("Unexpected kind {} in write_preproc_undefine.", kind),
+            _ => writer.unexpected(&child, "write_preproc_undefine"),
         }
     }
 
@@ -142,7 +142,7 @@ pub fn write_preproc_generic(node: &Node, writer: &mut Writer) -> Result<(), Utf
             }
             "preproc_arg" => write_preproc_arg(&child, writer)?,
             "comment" => write_comment(&child, writer)?,
-            _ => println!("Unexpected kind {} in write_preproc_generic.", kind),
+            _ => writer.unexpected(&child, "write_preproc_generic"),
         }
     }
 
@@ -167,7 +167,7 @@ pub fn write_preproc_symbol(node: &Node, writer: &mut Writer) -> Result<(), Utf8
         "preproc_endif" | "preproc_else" | "preproc_endinput" | "symbol" => {
             write_node(&node, writer)?
         }
-        _ => println!("Unexpected kind {} in write_preproc_symbol.", kind),
+        _ => writer.unexpected(node, "write_preproc_symbol"),
     }
 
     insert_break(&node, writer);
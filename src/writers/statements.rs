@@ -3,8 +3,10 @@ use std::{borrow::Borrow, str::Utf8Error};
 use tree_sitter::Node;
 
 use super::{
-    expressions::write_expression, old_variables::write_old_variable_declaration_statement,
-    variables::write_variable_declaration_statement, write_comment, write_node, Writer,
+    expressions::write_expression, has_error_outside_blocks,
+    old_variables::write_old_variable_declaration_statement,
+    variables::write_variable_declaration_statement, write_children, write_comment, write_node,
+    write_verbatim, Writer,
 };
 
 pub fn write_statement(
@@ -16,6 +18,40 @@ pub fn write_statement(
     let sp = node.end_position().row();
     let next_sibling = node.next_sibling();
 
+    if has_error_outside_blocks(&node) {
+        // Only indented when the statement starts a line of the output.
+        write_verbatim(&node, writer, "write_statement")?;
+    } else {
+        write_statement_kind(node, writer, do_indent)?;
+    }
+    if do_break {
+        if next_sibling.is_none() {
+            writer.breakl();
+            return Ok(());
+        }
+        let st = next_sibling.as_ref().unwrap().start_position().row();
+
+        // Don't add a break if the next sibling is a trailing comment.
+        if next_sibling.as_ref().unwrap().kind() == "comment" {
+            let st = next_sibling.unwrap().start_position().row();
+            if st - sp == 0 {
+                return Ok(());
+            }
+        }
+
+        // Add another break if the next sibling is not right below/next
+        // to the current sibling.
+        if st - sp > 1 {
+            writer.breakl();
+        }
+
+        writer.breakl();
+    }
+
+    Ok(())
+}
+
+fn write_statement_kind(node: Node, writer: &mut Writer, do_indent: bool) -> Result<(), Utf8Error> {
     match node.kind().borrow() {
         "block" => write_block(node, writer, do_indent)?,
         "variable_declaration_statement" => {
@@ -53,29 +89,6 @@ pub fn write_statement(
         }
         _ => write_node(&node, writer)?,
     }
-    if do_break {
-        if next_sibling.is_none() {
-            writer.breakl();
-            return Ok(());
-        }
-        let st = next_sibling.as_ref().unwrap().start_position().row();
-
-        // Don't add a break if the next sibling is a trailing comment.
-        if next_sibling.as_ref().unwrap().kind() == "comment" {
-            let st = next_sibling.unwrap().start_position().row();
-            if st - sp == 0 {
-                return Ok(());
-            }
-        }
-
-        // Add another break if the next sibling is not right below/next
-        // to the current sibling.
-        if st - sp > 1 {
-            writer.breakl();
-        }
-
-        writer.breakl();
-    }
 
     Ok(())
 }
@@ -533,9 +546,7 @@ fn write_condition_statement(
 }
 
 pub fn write_block(node: Node, writer: &mut Writer, do_indent: bool) -> Result<(), Utf8Error> {
-    let mut cursor = node.walk();
-
-    for child in node.children(&mut cursor) {
+    write_children(&node, writer, |child, writer| {
         let kind = child.kind();
         match kind.borrow() {
             "{" => {
@@ -553,14 +564,14 @@ pub fn write_block(node: Node, writer: &mut Writer, do_indent: bool) -> Result<(
             }
             "comment" => write_comment(&child, writer)?,
             _ => {
-                if writer.is_statement(&kind) {
+                if writer.is_statement(&kind) || child.is_error() {
                     write_statement(child, writer, true, true)?
                 } else {
                     write_node(&child, writer)?
                 }
             }
         }
-    }
 
-    Ok(())
+        Ok(())
+    })
 }
@@ -1,5 +1,7 @@
 use super::expressions::write_expression;
-use super::{write_comment, write_dimension, write_fixed_dimension, write_node, Writer};
+use super::{
+    write_children, write_comment, write_dimension, write_fixed_dimension, write_node, Writer,
+};
 use std::{borrow::Borrow, str::Utf8Error};
 
 use tree_sitter::Node;
@@ -14,7 +16,7 @@ pub fn write_struct_declaration(node: Node, writer: &mut Writer) -> Result<(), U
                 writer.output.push(' ');
             }
             "comment" => {
-                writer.output.push('\t');
+                writer.output.push_str(writer.indent_string.as_str());
                 write_comment(&sub_node, writer)?;
             }
             "=" => {
@@ -35,7 +37,7 @@ fn write_struct_constructor(node: Node, writer: &mut Writer) -> Result<(), Utf8E
         let kind = sub_node.kind();
         match kind.borrow() {
             "comment" => {
-                writer.output.push('\t');
+                writer.output.push_str(writer.indent_string.as_str());
                 write_comment(&sub_node, writer)?;
             }
             "struct_field_value" => write_struct_field_value(sub_node, writer)?,
@@ -49,7 +51,7 @@ fn write_struct_constructor(node: Node, writer: &mut Writer) -> Result<(), Utf8E
             }
             ";" => writer.output.push(';'),
             "," => continue,
-            _ => println!("Unexpected kind {} in write_struct_constructor.", kind),
+            _ => writer.unexpected(&sub_node, "write_struct_constructor"),
         }
     }
     if !writer.output.ends_with(';') {
@@ -65,15 +67,13 @@ fn write_struct_field_value(node: Node, writer: &mut Writer) -> Result<(), Utf8E
     for sub_node in node.children(&mut cursor) {
         match sub_node.kind().borrow() {
             "comment" => {
-                writer.output.push('\t');
+                writer.output.push_str(writer.indent_string.as_str());
                 write_comment(&sub_node, writer)?;
             }
             "symbol" => {
                 if key {
                     key = false;
-                    writer
-                        .output
-                        .push_str(writer.indent_string.repeat(writer.indent).as_str());
+                    writer.write_indent();
                     write_node(&sub_node, writer)?;
                 } else {
                     key = true;
@@ -93,11 +93,10 @@ fn write_struct_field_value(node: Node, writer: &mut Writer) -> Result<(), Utf8E
 }
 
 pub fn write_struct(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    let mut cursor = node.walk();
-    for sub_node in node.children(&mut cursor) {
+    write_children(&node, writer, |sub_node, writer| {
         match sub_node.kind().borrow() {
             "comment" => {
-                writer.output.push('\t');
+                writer.output.push_str(writer.indent_string.as_str());
                 write_comment(&sub_node, writer)?;
             }
             "struct" => writer.output.push_str("struct "),
@@ -113,15 +112,13 @@ pub fn write_struct(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
             "struct_field" => write_struct_field(sub_node, writer)?,
             _ => writer.output.push_str(";\n"),
         }
-    }
 
-    Ok(())
+        Ok(())
+    })
 }
 
 fn write_struct_field(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
-    writer
-        .output
-        .push_str(writer.indent_string.repeat(writer.indent).as_str());
+    writer.write_indent();
 
     let mut cursor = node.walk();
     for sub_node in node.children(&mut cursor) {
@@ -137,7 +134,7 @@ fn write_struct_field(node: Node, writer: &mut Writer) -> Result<(), Utf8Error>
             "fixed_dimension" => write_fixed_dimension(sub_node, writer, true)?,
             "dimension" => write_dimension(sub_node, writer, true)?,
             ";" => writer.output.push(';'),
-            _ => println!("Unexpected kind {} in write_struct_field.", kind),
+            _ => writer.unexpected(&sub_node, "write_struct_field"),
         }
     }
 
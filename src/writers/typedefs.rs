@@ -4,8 +4,8 @@ use tree_sitter::Node;
 
 use super::{
     functions::write_argument_declarations, next_sibling_kind, prev_sibling_kind,
-    variables::write_type, write_comment, write_dimension, write_fixed_dimension, write_node,
-    Writer,
+    variables::write_type, write_children, write_comment, write_dimension, write_fixed_dimension,
+    write_node, Writer,
 };
 
 pub fn write_typedef(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
@@ -14,7 +14,7 @@ pub fn write_typedef(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
 
     if !prev_kind.starts_with("preproc_") && prev_kind != "" && prev_kind != "comment" {
         // Insert new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
     let mut cursor = node.walk();
@@ -28,7 +28,7 @@ pub fn write_typedef(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
             "typedef_expression" => write_typedef_expression(child, writer)?,
             ";" => continue,
             _ => {
-                println!("Unexpected kind {} in write_typedef.", kind);
+                writer.unexpected(&child, "write_typedef");
             }
         }
     }
@@ -44,29 +44,15 @@ pub fn write_typeset(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
 
     if !prev_kind.starts_with("preproc_") && prev_kind != "" && prev_kind != "comment" {
         // Insert new lines automatically
-        writer.output.push_str("\n".repeat(nb_lines).as_str());
+        writer.write_empty_lines(nb_lines);
     }
 
-    let mut cursor = node.walk();
-
-    for child in node.children(&mut cursor) {
+    write_children(&node, writer, |child, writer| {
         let kind = child.kind();
         match kind.borrow() {
             "typeset" => writer.output.push_str("typeset "),
             "symbol" => write_node(&child, writer)?,
-            "{" => {
-                if writer.settings.brace_wrapping_before_typeset {
-                    writer.breakl();
-                } else {
-                    writer.output.push(' ');
-                }
-                writer.output.push_str("{\n");
-                writer.indent += 1;
-            }
-            "}" => {
-                writer.output.push_str("}");
-                writer.indent -= 1;
-            }
+            "{" | "}" => write_typeset_brace(&child, writer),
             "typedef_expression" => {
                 let next_kind = next_sibling_kind(&child);
                 write_typedef_expression(child, writer)?;
@@ -77,18 +63,41 @@ pub fn write_typeset(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
                 }
             }
             "comment" => write_comment(&child, writer)?,
-            ";" => continue,
+            ";" => {}
             _ => {
-                println!("Unexpected kind {} in write_typeset.", kind);
+                writer.unexpected(&child, "write_typeset");
             }
         }
-    }
+
+        Ok(())
+    })?;
     writer.output.push(';');
     writer.breakl();
 
     Ok(())
 }
 
+/// Write a brace of the body of a typeset, and update the indentation.
+///
+/// # Arguments
+///
+/// * `node`   - The brace node to write.
+/// * `writer` - The writer object.
+pub fn write_typeset_brace(node: &Node, writer: &mut Writer) {
+    if node.kind() == "}" {
+        writer.output.push_str("}");
+        writer.indent -= 1;
+        return;
+    }
+    if writer.settings.brace_wrapping_before_typeset {
+        writer.breakl();
+    } else {
+        writer.output.push(' ');
+    }
+    writer.output.push_str("{\n");
+    writer.indent += 1;
+}
+
 fn write_typedef_expression(node: Node, writer: &mut Writer) -> Result<(), Utf8Error> {
     let mut cursor = node.walk();
 
@@ -104,7 +113,7 @@ fn write_typedef_expression(node: Node, writer: &mut Writer) -> Result<(), Utf8E
             "argument_declarations" => write_argument_declarations(child, writer)?,
             "(" | ")" => continue,
             _ => {
-                println!("Unexpected kind {} in write_typedef_expression.", kind);
+                writer.unexpected(&child, "write_typedef_expression");
             }
         }
     }
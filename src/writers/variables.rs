@@ -1,6 +1,7 @@
 use super::{
     expressions::write_expression, next_sibling_kind, node_len, preproc::insert_break,
-    write_comment, write_dimension, write_dynamic_array, write_fixed_dimension, write_node, Writer,
+    prev_sibling_kind, write_comment, write_dimension, write_dynamic_array, write_fixed_dimension,
+    write_node, Writer,
 };
 use std::{borrow::Borrow, str::Utf8Error};
 
@@ -15,34 +16,76 @@ use tree_sitter::Node;
 pub fn write_global_variable_declaration(
     node: &Node,
     writer: &mut Writer,
+) -> Result<(), Utf8Error> {
+    write_global_declaration(node, writer, None)
+}
+
+/// Write an old global variable declaration converted to the new syntax.
+///
+/// # Arguments
+///
+/// * `node`   - The old global variable declaration node to write.
+/// * `writer` - The writer object.
+/// * `type_`  - The new syntax type of the declared variables.
+pub fn write_converted_global_variable_declaration(
+    node: &Node,
+    writer: &mut Writer,
+    type_: &str,
+) -> Result<(), Utf8Error> {
+    write_global_declaration(node, writer, Some(type_))
+}
+
+/// Write a global variable declaration, in the new or the old syntax. The old
+/// syntax declarations are converted when a type is given.
+///
+/// # Arguments
+///
+/// * `node`           - The global variable declaration node to write.
+/// * `writer`         - The writer object.
+/// * `converted_type` - The new syntax type of an old syntax declaration.
+fn write_global_declaration(
+    node: &Node,
+    writer: &mut Writer,
+    converted_type: Option<&str>,
 ) -> Result<(), Utf8Error> {
     let mut cursor = node.walk();
 
-    let max_name_length = get_max_variable_name_length(&node)?;
+    let max_name_length = get_max_variable_name_length(&node, writer)?;
 
-    // Keep track of the type's length (as well as the storage class and visibility)
-    // to properly indent line break variables.
-    // Start at `1` to account for the ` ` between the type and the declaration.
-    let mut type_length = 1;
+    // Keep track of the column of the first declaration to properly
+    // align line break variables.
+    let mut declaration_column = 0;
     for child in node.children(&mut cursor) {
         let kind = child.kind();
         match kind.borrow() {
-            "type" => {
-                write_type(&child, writer)?;
-                type_length += node_len(&child);
-            }
+            "type" => write_type(&child, writer)?,
             "variable_storage_class" | "variable_visibility" => {
                 write_node(&child, writer)?;
                 writer.output.push(' ');
-                type_length += node_len(&child) + 1;
             }
             "comment" => {
                 write_comment(&child, writer)?;
                 if max_name_length > 0 {
-                    writer.output.push_str(" ".repeat(type_length).as_str());
+                    writer.align_to(declaration_column);
+                }
+            }
+            "variable_declaration" => {
+                if declaration_column == 0 {
+                    declaration_column = writer.column();
                 }
+                write_variable_declaration(&child, writer, max_name_length, None)?
+            }
+            "new" | "decl" if converted_type.is_some() => continue,
+            "old_variable_declaration" if converted_type.is_some() => {
+                if declaration_column == 0 {
+                    // The type is written before the first declaration, after
+                    // the storage class.
+                    writer.output.push_str(converted_type.unwrap());
+                    writer.output.push(' ');
+                    declaration_column = writer.column();
+                }
+                write_variable_declaration(&child, writer, max_name_length, converted_type)?
             }
-            "variable_declaration" => write_variable_declaration(&child, writer, max_name_length)?,
             "," => {
                 if max_name_length > 0 {
                     let next_kind = next_sibling_kind(&child);
@@ -50,14 +93,14 @@ pub fn write_global_variable_declaration(
                         writer.output.push_str(",");
                     } else {
                         writer.output.push_str(",\n");
-                        writer.output.push_str(" ".repeat(type_length).as_str());
+                        writer.align_to(declaration_column);
                     }
                 } else {
                     writer.output.push_str(", ")
                 }
             }
             ";" => continue,
-            _ => println!("Unexpected kind {} in write_global_variable.", kind),
+            _ => writer.unexpected(&child, "write_global_variable_declaration"),
         }
     }
     writer.output.push(';');
@@ -91,7 +134,8 @@ pub fn write_type(node: &Node, writer: &mut Writer) -> Result<(), Utf8Error> {
 /// # Arguments
 ///
 /// * `node`   - The node which has the variable declarations.
-fn get_max_variable_name_length(node: &Node) -> Result<usize, Utf8Error> {
+/// * `writer` - The writer object.
+fn get_max_variable_name_length(node: &Node, writer: &Writer) -> Result<usize, Utf8Error> {
     let mut cursor = node.walk();
 
     // Compute an estimated length of the declarations.
@@ -103,7 +147,7 @@ fn get_max_variable_name_length(node: &Node) -> Result<usize, Utf8Error> {
     for child in node.children(&mut cursor) {
         let kind = child.kind();
         match kind.borrow() {
-            "variable_declaration" => {
+            "variable_declaration" | "old_variable_declaration" => {
                 // FIXME: This includes whitespaces, and might yield incorrect results.
                 // Sum the length of each node once formatted instead.
                 length += node_len(&child);
@@ -120,6 +164,8 @@ fn get_max_variable_name_length(node: &Node) -> Result<usize, Utf8Error> {
                         "symbol" => name_length += node_len(&sub_child),
                         "dimension" => name_length += 2,
                         "fixed_dimension" => name_length += node_len(&sub_child),
+                        // The tag of an old declaration is not written when it is converted.
+                        "old_type" => length -= node_len(&sub_child),
                         _ => continue,
                     }
                 }
@@ -134,7 +180,7 @@ fn get_max_variable_name_length(node: &Node) -> Result<usize, Utf8Error> {
         }
     }
 
-    if length <= 80 && !nested_comment {
+    if !writer.exceeds_line_width(length) && !nested_comment {
         max_name_length = 0;
     }
 
@@ -145,21 +191,56 @@ fn get_max_variable_name_length(node: &Node) -> Result<usize, Utf8Error> {
 ///
 /// # Arguments
 ///
-/// * `node`   - The variable declaration statement node to write.
-/// * `writer` - The writer object.
+/// * `node`      - The variable declaration statement node to write.
+/// * `writer`    - The writer object.
+/// * `do_indent` - Whether or not to indent the statement and end it with a `;`.
 pub fn write_variable_declaration_statement(
     node: Node,
     writer: &mut Writer,
     do_indent: bool,
+) -> Result<(), Utf8Error> {
+    write_declaration_statement(node, writer, do_indent, None)
+}
+
+/// Write an old variable declaration statement converted to the new syntax.
+///
+/// # Arguments
+///
+/// * `node`      - The old variable declaration statement node to write.
+/// * `writer`    - The writer object.
+/// * `do_indent` - Whether or not to indent the statement and end it with a `;`.
+/// * `type_`     - The new syntax type of the declared variables.
+pub fn write_converted_variable_declaration_statement(
+    node: Node,
+    writer: &mut Writer,
+    do_indent: bool,
+    type_: &str,
+) -> Result<(), Utf8Error> {
+    write_declaration_statement(node, writer, do_indent, Some(type_))
+}
+
+/// Write a variable declaration statement, in the new or the old syntax. The old
+/// syntax declarations are converted when a type is given.
+///
+/// # Arguments
+///
+/// * `node`           - The variable declaration statement node to write.
+/// * `writer`         - The writer object.
+/// * `do_indent`      - Whether or not to indent the statement and end it with a `;`.
+/// * `converted_type` - The new syntax type of an old syntax declaration.
+fn write_declaration_statement(
+    node: Node,
+    writer: &mut Writer,
+    do_indent: bool,
+    converted_type: Option<&str>,
 ) -> Result<(), Utf8Error> {
     let mut cursor = node.walk();
 
-    let max_name_length = get_max_variable_name_length(&node)?;
+    let max_name_length = get_max_variable_name_length(&node, writer)?;
 
-    // Keep track of the type's length (as well as the storage class and visibility)
-    // to properly indent line break variables.
-    // Start at `1` to account for the ` ` between the type and the declaration.
-    let mut type_length = 1;
+    // Keep track of the column of the first declaration to properly
+    // align line break variables.
+    let mut declaration_column = 0;
     if do_indent {
         writer.write_indent();
     }
@@ -167,26 +248,35 @@ pub fn write_variable_declaration_statement(
     for child in node.children(&mut cursor) {
         let kind = child.kind();
         match kind.borrow() {
-            "type" => {
-                write_type(&child, writer)?;
-                type_length += node_len(&child);
-            }
+            "type" => write_type(&child, writer)?,
             "variable_storage_class" | "variable_visibility" => {
                 write_node(&child, writer)?;
                 writer.output.push(' ');
-                type_length += node_len(&child) + 1;
             }
             "comment" => {
                 write_comment(&child, writer)?;
                 if max_name_length > 0 {
-                    if do_indent {
-                        writer.write_indent();
-                    }
-                    writer.output.push_str(" ".repeat(type_length).as_str());
+                    writer.align_to(declaration_column);
                 }
             }
             "dimension" => write_dimension(child, writer, true)?,
-            "variable_declaration" => write_variable_declaration(&child, writer, max_name_length)?,
+            "variable_declaration" => {
+                if declaration_column == 0 {
+                    declaration_column = writer.column();
+                }
+                write_variable_declaration(&child, writer, max_name_length, None)?
+            }
+            "new" | "decl" if converted_type.is_some() => continue,
+            "old_variable_declaration" if converted_type.is_some() => {
+                if declaration_column == 0 {
+                    // The type is written before the first declaration, after
+                    // the storage class.
+                    writer.output.push_str(converted_type.unwrap());
+                    writer.output.push(' ');
+                    declaration_column = writer.column();
+                }
+                write_variable_declaration(&child, writer, max_name_length, converted_type)?
+            }
             "," => {
                 if max_name_length > 0 {
                     let next_kind = next_sibling_kind(&child);
@@ -194,17 +284,14 @@ pub fn write_variable_declaration_statement(
                         writer.output.push_str(",");
                     } else {
                         writer.output.push_str(",\n");
-                        if do_indent {
-                            writer.write_indent();
-                        }
-                        writer.output.push_str(" ".repeat(type_length).as_str());
+                        writer.align_to(declaration_column);
                     }
                 } else {
                     writer.output.push_str(", ")
                 }
             }
             ";" => continue,
-            _ => println!("Unexpected kind {} in write_global_variable.", kind),
+            _ => writer.unexpected(&child, "write_variable_declaration_statement"),
         }
     }
 
@@ -219,13 +306,15 @@ pub fn write_variable_declaration_statement(
 ///
 /// # Arguments
 ///
-/// * `node`   - The variable declaration node to write.
-/// * `writer` - The writer object.
-/// * `writer` - The writer object.
+/// * `node`            - The variable declaration node to write.
+/// * `writer`          - The writer object.
+/// * `max_name_length` - The length to pad the names to before the `=`, or 0.
+/// * `converted_type`  - The new syntax type of an old syntax declaration.
 fn write_variable_declaration(
     node: &Node,
     writer: &mut Writer,
     max_name_length: usize,
+    converted_type: Option<&str>,
 ) -> Result<(), Utf8Error> {
     let mut cursor = node.walk();
 
@@ -236,6 +325,14 @@ fn write_variable_declaration(
     for child in node.children(&mut cursor) {
         let kind = child.kind();
         match kind.borrow() {
+            // `INVALID_HANDLE` is only replaced where it is known to be a `Handle`.
+            "symbol"
+                if converted_type == Some("Handle")
+                    && prev_sibling_kind(&child) == "="
+                    && child.utf8_text(writer.source)? == "INVALID_HANDLE" =>
+            {
+                writer.output.push_str("null")
+            }
             "symbol" => {
                 write_node(&child, writer)?;
                 name_length += node_len(&child);
@@ -257,11 +354,12 @@ fn write_variable_declaration(
                 writer.output.push_str(" = ");
             }
             "dynamic_array" => write_dynamic_array(child, writer)?,
+            "old_type" => continue,
             _ => {
                 if writer.is_expression(&kind) {
                     write_expression(child, writer)?
                 } else {
-                    println!("Unexpected kind {} in write_global_variable.", kind);
+                    writer.unexpected(&child, "write_variable_declaration");
                 }
             }
         }
@@ -0,0 +1,62 @@
+//! Check that the coverage report classifies the nodes by what the writers did
+//! with them.
+
+use sp_format::{
+    coverage::{Coverage, KindCoverage},
+    settings::Settings,
+};
+
+/// A function accessing an array through a field, whose field access is copied
+/// verbatim by the writer of the array access.
+const SOURCE: &str = "void Foo()\n{\n\tg_Player.data[0] = 1;\n}\n";
+
+#[test]
+fn coverage_counts_the_verbatim_nodes() {
+    let mut coverage = Coverage::new();
+    coverage
+        .add_source(&SOURCE.to_string(), &Settings::default())
+        .unwrap();
+
+    assert_eq!(coverage.sources, 1);
+    assert_eq!(
+        coverage.kinds["field_access"],
+        KindCoverage {
+            formatted: 0,
+            verbatim: 1,
+            unexpected: 0,
+        }
+    );
+    assert_eq!(coverage.kinds["array_indexed_access"].formatted, 1);
+    assert_eq!(coverage.kinds["assignment_expression"].formatted, 1);
+    assert!(coverage.verbatim().contains(&"field_access"));
+    assert!(!coverage.unseen().contains(&"field_access"));
+}
+
+#[test]
+fn coverage_report_lists_the_counts() {
+    let mut coverage = Coverage::new();
+    for _ in 0..2 {
+        coverage
+            .add_source(&SOURCE.to_string(), &Settings::default())
+            .unwrap();
+    }
+    let report = coverage.to_string();
+
+    assert!(report.starts_with(&format!(
+        "Coverage of {} node kinds over 2 source(s).\n",
+        coverage.kinds.len()
+    )));
+    let verbatim = report
+        .split("\nCopied verbatim (")
+        .nth(1)
+        .and_then(|section| section.split("\nUnexpected (").next())
+        .unwrap();
+    assert!(verbatim.contains("\n  field_access (2)\n"));
+    let formatted = report
+        .split("\nFormatted (")
+        .nth(1)
+        .and_then(|section| section.split("\nCopied verbatim (").next())
+        .unwrap();
+    assert!(formatted.contains("\n  assignment_expression (2)\n"));
+    assert!(!formatted.contains("\n  field_access ("));
+}
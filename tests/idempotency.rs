@@ -0,0 +1,101 @@
+//! Check that formatting the formatted code of the corpus leaves it unchanged.
+//!
+//! The corpus is made of one file per writer module, in `tests/corpus`.
+
+use std::{fs, path::PathBuf};
+
+use sp_format::{
+    check_idempotency,
+    settings::{IndentStyle, Settings},
+};
+
+/// Settings differing from the defaults on every field, to also cover the
+/// other code paths of the writers.
+fn custom_settings() -> Settings {
+    Settings {
+        breaks_before_function_decl: 1,
+        breaks_before_function_def: 0,
+        breaks_before_enum: 1,
+        breaks_before_enum_struct: 1,
+        breaks_before_methodmap: 0,
+        brace_wrapping_before_function: false,
+        brace_wrapping_before_loop: false,
+        brace_wrapping_before_condition: false,
+        brace_wrapping_before_enum_struct: false,
+        brace_wrapping_before_enum: false,
+        brace_wrapping_before_typeset: false,
+        brace_wrapping_before_funcenum: false,
+        brace_wrapping_before_methodmap: false,
+        brace_wrapping_before_methodmap_property: false,
+        indent_style: IndentStyle::Spaces,
+        indent_width: 2,
+        max_line_width: 40,
+        remove_redundant_parentheses: true,
+        add_clarifying_parentheses: true,
+        bin_pack_arguments: true,
+        fill_array_literals: true,
+        trailing_comma_in_array_literals: true,
+        align_array_literal_columns: true,
+        break_before_logical_operators: true,
+        align_argument_names: true,
+        convert_old_syntax: true,
+        ..Default::default()
+    }
+}
+
+/// Assert that a file of the corpus is formatted idempotently, with the
+/// default and the custom settings.
+///
+/// # Arguments
+///
+/// * `name` - Name of the file of the corpus, without its extension.
+fn assert_idempotent(name: &str) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "corpus", name]
+        .iter()
+        .collect::<PathBuf>()
+        .with_extension("sp");
+    let source = fs::read_to_string(&path).unwrap();
+    for (settings_name, settings) in [
+        ("default", Settings::default()),
+        ("custom", custom_settings()),
+    ] {
+        if let Err(err) = check_idempotency(&source, settings) {
+            panic!(
+                "{} with the {} settings: {}",
+                path.display(),
+                settings_name,
+                err
+            );
+        }
+    }
+}
+
+macro_rules! idempotency_tests {
+    ($($name:ident),* $(,)?) => {
+        $(
+            #[test]
+            fn $name() {
+                assert_idempotent(stringify!($name));
+            }
+        )*
+    };
+}
+
+idempotency_tests!(
+    alias,
+    assertions,
+    enum_structs,
+    enums,
+    expressions,
+    functags,
+    functions,
+    hardcoded_symbols,
+    methodmaps,
+    old_variables,
+    preproc,
+    source_file,
+    statements,
+    structs,
+    typedefs,
+    variables,
+);
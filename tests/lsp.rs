@@ -0,0 +1,227 @@
+//! Check the requests and notifications handled by the language server, with a
+//! client talking to it over an in-memory connection.
+
+use std::thread::{self, JoinHandle};
+
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    notification::{
+        DidChangeConfiguration, DidOpenTextDocument, Exit, Initialized, Notification as _,
+        ShowMessage,
+    },
+    request::{Formatting, Initialize, RangeFormatting, Request as _, Shutdown},
+    DidChangeConfigurationParams, DidOpenTextDocumentParams, DocumentFormattingParams,
+    DocumentRangeFormattingParams, FormattingOptions, InitializeParams, InitializedParams,
+    MessageType, Position, Range, ShowMessageParams, TextDocumentIdentifier, TextDocumentItem,
+    TextEdit, Url,
+};
+use serde::Serialize;
+use serde_json::json;
+
+/// Start a server in another thread and initialize it.
+///
+/// # Arguments
+///
+/// * `options` - The initialization options, i.e. the settings of the formatter.
+fn start(options: serde_json::Value) -> (Connection, JoinHandle<anyhow::Result<()>>) {
+    let (server, client) = Connection::memory();
+    let handle = thread::spawn(move || sp_format::lsp::serve(&server));
+    let params = InitializeParams {
+        initialization_options: Some(options),
+        ..Default::default()
+    };
+    request(&client, 0, Initialize::METHOD, params);
+    notify(&client, Initialized::METHOD, InitializedParams {});
+
+    (client, handle)
+}
+
+/// Shut the server down, and wait for it to exit.
+///
+/// # Arguments
+///
+/// * `client` - The connection of the client.
+/// * `server` - The thread of the server.
+fn stop(client: Connection, server: JoinHandle<anyhow::Result<()>>) {
+    request(&client, 1000, Shutdown::METHOD, ());
+    notify(&client, Exit::METHOD, ());
+    server.join().unwrap().unwrap();
+}
+
+/// Send a request to the server, and return its response.
+///
+/// # Arguments
+///
+/// * `client` - The connection of the client.
+/// * `id`     - The id of the request.
+/// * `method` - The method of the request.
+/// * `params` - The parameters of the request.
+fn request<P: Serialize>(client: &Connection, id: i32, method: &str, params: P) -> Response {
+    let request = Request::new(RequestId::from(id), method.to_string(), params);
+    client.sender.send(Message::Request(request)).unwrap();
+    match client.receiver.recv().unwrap() {
+        Message::Response(response) => response,
+        message => panic!("Expected a response, got {:?}", message),
+    }
+}
+
+/// Send a notification to the server.
+///
+/// # Arguments
+///
+/// * `client` - The connection of the client.
+/// * `method` - The method of the notification.
+/// * `params` - The parameters of the notification.
+fn notify<P: Serialize>(client: &Connection, method: &str, params: P) {
+    let notification = Notification::new(method.to_string(), params);
+    client
+        .sender
+        .send(Message::Notification(notification))
+        .unwrap();
+}
+
+/// Open a document in the server.
+///
+/// # Arguments
+///
+/// * `client` - The connection of the client.
+/// * `text`   - The content of the document.
+fn open(client: &Connection, text: &str) -> Url {
+    let uri = Url::parse("file:///plugin.sp").unwrap();
+    let params = DidOpenTextDocumentParams {
+        text_document: TextDocumentItem::new(
+            uri.clone(),
+            "sourcepawn".to_string(),
+            1,
+            text.to_string(),
+        ),
+    };
+    notify(client, DidOpenTextDocument::METHOD, params);
+
+    uri
+}
+
+/// Returns the edits of the response of a formatting request.
+///
+/// # Arguments
+///
+/// * `response` - The response to the request.
+fn edits(response: Response) -> Vec<TextEdit> {
+    assert!(response.error.is_none(), "{:?}", response.error);
+    serde_json::from_value::<Option<Vec<TextEdit>>>(response.result.unwrap())
+        .unwrap()
+        .unwrap()
+}
+
+#[test]
+fn formatting_replaces_the_document() {
+    let (client, server) = start(json!({}));
+    let uri = open(&client, "int  g_Count;\n");
+    let params = DocumentFormattingParams {
+        text_document: TextDocumentIdentifier::new(uri),
+        options: FormattingOptions::default(),
+        work_done_progress_params: Default::default(),
+    };
+    let response = request(&client, 1, Formatting::METHOD, params);
+
+    assert_eq!(
+        edits(response),
+        vec![TextEdit::new(
+            Range::new(Position::new(0, 0), Position::new(1, 0)),
+            "int g_Count;\n".to_string()
+        )]
+    );
+    stop(client, server);
+}
+
+#[test]
+fn range_formatting_counts_utf16_code_units() {
+    let (client, server) = start(json!({}));
+    // `é` is one UTF-16 code unit and two bytes, `😀` is two code units and four bytes.
+    let uri = open(&client, "char g_Name[] = \"é😀\"; int  g_Count;\n");
+    let params = DocumentRangeFormattingParams {
+        text_document: TextDocumentIdentifier::new(uri),
+        range: Range::new(Position::new(0, 23), Position::new(0, 36)),
+        options: FormattingOptions::default(),
+        work_done_progress_params: Default::default(),
+    };
+    let response = request(&client, 1, RangeFormatting::METHOD, params);
+
+    assert_eq!(
+        edits(response),
+        vec![TextEdit::new(
+            Range::new(Position::new(0, 23), Position::new(0, 36)),
+            "int g_Count;".to_string()
+        )]
+    );
+    stop(client, server);
+}
+
+/// Format a document containing a single function, and returns its new text.
+///
+/// # Arguments
+///
+/// * `client`  - The connection of the client.
+/// * `options` - The formatting options of the request.
+fn format_function(client: &Connection, options: FormattingOptions) -> String {
+    let uri = open(client, "void Foo()\n{\ng_Count++;\n}\n");
+    let params = DocumentFormattingParams {
+        text_document: TextDocumentIdentifier::new(uri),
+        options,
+        work_done_progress_params: Default::default(),
+    };
+    let response = request(client, 1, Formatting::METHOD, params);
+
+    edits(response).remove(0).new_text
+}
+
+#[test]
+fn settings_of_other_tools_are_ignored() {
+    let (client, server) =
+        start(json!({ "sp_format": { "indent_style": "spaces", "indent_width": 2 } }));
+    let params = DidChangeConfigurationParams {
+        settings: json!({ "editor": { "tabSize": 8 } }),
+    };
+    notify(&client, DidChangeConfiguration::METHOD, params);
+
+    assert_eq!(
+        format_function(&client, FormattingOptions::default()),
+        "void Foo()\n{\n  g_Count++;\n}\n"
+    );
+    stop(client, server);
+}
+
+#[test]
+fn indentation_follows_the_formatting_options_without_settings() {
+    let (client, server) = start(json!({}));
+    let options = FormattingOptions {
+        tab_size: 3,
+        insert_spaces: true,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        format_function(&client, options),
+        "void Foo()\n{\n   g_Count++;\n}\n"
+    );
+    stop(client, server);
+}
+
+#[test]
+fn invalid_settings_are_shown_to_the_user() {
+    let (client, server) = start(json!({}));
+    let params = DidChangeConfigurationParams {
+        settings: json!({ "sp_format": { "indent_width": "wide" } }),
+    };
+    notify(&client, DidChangeConfiguration::METHOD, params);
+
+    match client.receiver.recv().unwrap() {
+        Message::Notification(notification) => {
+            assert_eq!(notification.method, ShowMessage::METHOD);
+            let params: ShowMessageParams = serde_json::from_value(notification.params).unwrap();
+            assert_eq!(params.typ, MessageType::ERROR);
+        }
+        message => panic!("Expected a notification, got {:?}", message),
+    }
+    stop(client, server);
+}
@@ -0,0 +1,81 @@
+//! Check that formatting a range of a source only changes the nodes which
+//! cover the range, and formats them like the whole source would be.
+
+use sp_format::{format_lines, format_range, format_string, settings::Settings, TextEdit};
+
+/// Apply edits to a source.
+///
+/// # Arguments
+///
+/// * `input` - The source to edit.
+/// * `edits` - The edits to apply, which do not overlap.
+fn apply_edits(input: &str, mut edits: Vec<TextEdit>) -> String {
+    let mut output = input.to_string();
+    edits.sort_by_key(|edit| edit.range.start);
+    for edit in edits.into_iter().rev() {
+        output.replace_range(edit.range, &edit.new_text);
+    }
+
+    output
+}
+
+#[test]
+fn range_keeps_the_rest_of_the_source() {
+    let input = "void Foo()\n{\n\tint x=1;\n\tint  y = 2;\n}\n\nint  g_Count;\n".to_string();
+    let edits = format_lines(&input, 2..3, Settings::default()).unwrap();
+
+    assert_eq!(
+        apply_edits(&input, edits),
+        "void Foo()\n{\n\tint x = 1;\n\tint  y = 2;\n}\n\nint  g_Count;\n"
+    );
+}
+
+#[test]
+fn range_terminates_the_statements() {
+    let input = "void Foo()\n{\n\tint x=1;\n\tFoo( );\n\treturn;\n}\n".to_string();
+    let start = input.find("int").unwrap();
+    let end = input.find("return").unwrap();
+    let edits = format_range(&input, start..end, Settings::default()).unwrap();
+
+    assert_eq!(
+        apply_edits(&input, edits),
+        "void Foo()\n{\n\tint x = 1;\n\tFoo();\n\treturn;\n}\n"
+    );
+}
+
+#[test]
+fn range_indents_from_the_nesting() {
+    let input = "void Foo()\n{\n\tif (a)\n\t{\n  int x = 1;\n\t}\n}\n".to_string();
+    let edits = format_lines(&input, 4..5, Settings::default()).unwrap();
+    let output = apply_edits(&input, edits);
+
+    assert_eq!(
+        output,
+        "void Foo()\n{\n\tif (a)\n\t{\n\t\tint x = 1;\n\t}\n}\n"
+    );
+    assert_eq!(output, format_string(&input, Settings::default()).unwrap());
+}
+
+#[test]
+fn range_of_a_top_level_node() {
+    let input = "int g_Count;\nint  g_Total=0;\n".to_string();
+    let edits = format_lines(&input, 1..2, Settings::default()).unwrap();
+
+    assert_eq!(
+        apply_edits(&input, edits),
+        "int g_Count;\nint g_Total = 0;\n"
+    );
+}
+
+#[test]
+fn range_keeps_the_disabled_nodes() {
+    let input =
+        "void Foo()\n{\n\t// sp-format ignore-next\n\tint x=1;\n\tint y=2;\n\t// sp-format off\n\tint z=3;\n}\n"
+            .to_string();
+    let edits = format_lines(&input, 3..7, Settings::default()).unwrap();
+
+    assert_eq!(
+        apply_edits(&input, edits),
+        "void Foo()\n{\n\t// sp-format ignore-next\n\tint x=1;\n\tint y = 2;\n\t// sp-format off\n\tint z=3;\n}\n"
+    );
+}
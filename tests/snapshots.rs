@@ -0,0 +1,99 @@
+//! Compare the output of the formatter with the expected output of the fixtures.
+//!
+//! Each `tests/fixtures/<writer>/<name>.sp` input is formatted and compared with
+//! `<name>.out.sp`. The settings are read from `<name>.toml` if it exists, and
+//! default to [`Settings::default`] otherwise. The outputs are verified, so that
+//! the fixtures of the transforms which add or remove parentheses also check that
+//! the grouping of the operations is kept.
+//!
+//! Run the tests with `SP_FORMAT_BLESS=1` to regenerate the expected outputs.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use similar::TextDiff;
+use sp_format::{config::load_config_file, format_string_verified, settings::Settings};
+
+/// Extension of the expected outputs, which are not inputs themselves.
+const OUTPUT_EXTENSION: &str = ".out.sp";
+
+/// Collect the inputs of the fixtures, sorted by path.
+fn collect_inputs() -> Vec<PathBuf> {
+    let pattern = format!("{}/tests/fixtures/*/*.sp", env!("CARGO_MANIFEST_DIR"));
+    let mut inputs: Vec<PathBuf> = glob::glob(&pattern)
+        .unwrap()
+        .map(Result::unwrap)
+        .filter(|path| !path.to_string_lossy().ends_with(OUTPUT_EXTENSION))
+        .collect();
+    inputs.sort();
+
+    inputs
+}
+
+/// Returns the settings of a fixture.
+///
+/// # Arguments
+///
+/// * `input` - Path of the input of the fixture.
+fn fixture_settings(input: &Path) -> Settings {
+    let path = input.with_extension("toml");
+    if !path.exists() {
+        return Settings::default();
+    }
+    load_config_file(&path).unwrap()
+}
+
+/// Format the input of a fixture and compare it with its expected output.
+/// Returns a description of the difference if they differ.
+///
+/// # Arguments
+///
+/// * `input` - Path of the input of the fixture.
+/// * `bless` - Whether or not to overwrite the expected output.
+fn check_fixture(input: &Path, bless: bool) -> Option<String> {
+    let expected_path = input.with_extension("out.sp");
+    let source = fs::read_to_string(input).unwrap();
+    let output = match format_string_verified(&source, fixture_settings(input)) {
+        Ok(formatted) => formatted.output,
+        Err(err) => return Some(format!("{}: {}", input.display(), err)),
+    };
+    if bless {
+        fs::write(&expected_path, &output).unwrap();
+        return None;
+    }
+    let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+    if expected == output {
+        return None;
+    }
+    let diff = TextDiff::from_lines(&expected, &output)
+        .unified_diff()
+        .header(
+            &expected_path.display().to_string(),
+            &format!("{} (formatted)", input.display()),
+        )
+        .to_string();
+
+    Some(diff)
+}
+
+#[test]
+fn snapshots() {
+    let bless = env::var_os("SP_FORMAT_BLESS").is_some();
+    let inputs = collect_inputs();
+    assert!(!inputs.is_empty(), "no fixture was found");
+
+    let failures: Vec<String> = inputs
+        .iter()
+        .filter_map(|input| check_fixture(input, bless))
+        .collect();
+    if !failures.is_empty() {
+        panic!(
+            "{} of {} fixtures differ, run the tests with SP_FORMAT_BLESS=1 to update them:\n\n{}",
+            failures.len(),
+            inputs.len(),
+            failures.join("\n")
+        );
+    }
+}